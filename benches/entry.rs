@@ -0,0 +1,36 @@
+#![feature(test)]
+
+extern crate diesel_pg_hstore;
+extern crate test;
+
+use test::Bencher;
+
+use diesel_pg_hstore::Hstore;
+
+const KEYS: [&str; 8] = ["a", "b", "c", "d", "e", "f", "g", "h"];
+
+fn settings() -> Hstore {
+    KEYS.iter()
+        .map(|k| (k.to_string(), "0".to_string()))
+        .collect()
+}
+
+#[bench]
+fn entry_string_key(b: &mut Bencher) {
+    let mut settings = settings();
+    b.iter(|| {
+        for key in KEYS.iter() {
+            test::black_box(settings.entry(key.to_string()).or_insert_with(String::new).len());
+        }
+    });
+}
+
+#[bench]
+fn entry_str_key(b: &mut Bencher) {
+    let mut settings = settings();
+    b.iter(|| {
+        for key in KEYS.iter() {
+            test::black_box(settings.entry_str(key).or_insert_with(String::new).len());
+        }
+    });
+}
@@ -0,0 +1,79 @@
+#![feature(test)]
+
+extern crate diesel_pg_hstore;
+extern crate test;
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::io::Write;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use test::Bencher;
+
+use diesel_pg_hstore::Hstore;
+
+/// Allocator keeping track of the peak number of bytes allocated at once
+struct PeakAlloc;
+
+static CURRENT: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for PeakAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let current = CURRENT.fetch_add(layout.size(), Ordering::SeqCst) + layout.size();
+        PEAK.fetch_max(current, Ordering::SeqCst);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        CURRENT.fetch_sub(layout.size(), Ordering::SeqCst);
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: PeakAlloc = PeakAlloc;
+
+fn large() -> Hstore {
+    (0..1000)
+        .map(|i| (format!("key{}", i), "x".repeat(1000)))
+        .collect()
+}
+
+/// Run `f` once, returning the peak allocation above what was live beforehand
+fn peak_during<F: FnMut()>(mut f: F) -> usize {
+    let before = CURRENT.load(Ordering::SeqCst);
+    PEAK.store(before, Ordering::SeqCst);
+    f();
+    PEAK.load(Ordering::SeqCst) - before
+}
+
+// Both benches write into a reused buffer standing in for diesel's output, so the peak only
+// counts what the encoding itself allocates.
+
+#[bench]
+fn buffered(b: &mut Bencher) {
+    let settings = large();
+    let mut out = Vec::with_capacity(settings.wire_size());
+    let mut encode = || {
+        out.clear();
+        out.write_all(&settings.to_wire_bytes().unwrap()).unwrap();
+        test::black_box(&out);
+    };
+
+    println!("buffered peak allocation: {} bytes", peak_during(&mut encode));
+    b.iter(encode);
+}
+
+#[bench]
+fn streamed(b: &mut Bencher) {
+    let settings = large();
+    let mut out = Vec::with_capacity(settings.wire_size());
+    let mut encode = || {
+        out.clear();
+        settings.write_wire_bytes(&mut out).unwrap();
+        test::black_box(&out);
+    };
+
+    println!("streamed peak allocation: {} bytes", peak_during(&mut encode));
+    b.iter(encode);
+}
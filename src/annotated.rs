@@ -0,0 +1,115 @@
+//! Hstore entries carrying in-memory metadata
+
+use std::collections::HashMap;
+
+use super::Hstore;
+
+/// An Hstore where every entry carries a piece of metadata `M`
+///
+/// This is meant for tracking where entries came from, such as the configuration layer which set
+/// them. The metadata only lives in memory: converting to an Hstore drops it, so it is never
+/// written to the database.
+///
+/// ```rust
+/// use diesel_pg_hstore::{AnnotatedHstore, Hstore};
+///
+/// let mut defaults = Hstore::new();
+/// defaults.insert("theme".into(), "light".into());
+/// defaults.insert("lang".into(), "en".into());
+/// let mut user = Hstore::new();
+/// user.insert("theme".into(), "dark".into());
+///
+/// let mut settings = AnnotatedHstore::from_hstore(defaults, "defaults");
+/// settings.merge(AnnotatedHstore::from_hstore(user, "user"));
+///
+/// assert_eq!(settings.get("theme"), Some("dark"));
+/// assert_eq!(settings.metadata("theme"), Some(&"user"));
+/// assert_eq!(settings.metadata("lang"), Some(&"defaults"));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AnnotatedHstore<M>(HashMap<String, (String, M)>);
+
+impl<M> Default for AnnotatedHstore<M> {
+    fn default() -> Self {
+        AnnotatedHstore(HashMap::new())
+    }
+}
+
+impl<M> AnnotatedHstore<M> {
+    /// Create a new AnnotatedHstore object
+    pub fn new() -> AnnotatedHstore<M> {
+        AnnotatedHstore::default()
+    }
+
+    /// Create an AnnotatedHstore from the entries of `hstore`, all carrying `metadata`
+    pub fn from_hstore(hstore: Hstore, metadata: M) -> AnnotatedHstore<M>
+        where M: Clone
+    {
+        AnnotatedHstore(hstore.into_iter()
+            .map(|(k, v)| (k, (v, metadata.clone())))
+            .collect())
+    }
+
+    /// The number of entries
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Check if there are no entries
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Insert an entry with its metadata, returning the previous value and metadata if any
+    pub fn insert(&mut self, k: String, v: String, metadata: M) -> Option<(String, M)> {
+        self.0.insert(k, (v, metadata))
+    }
+
+    /// Remove an entry, returning its value and metadata
+    pub fn remove(&mut self, k: &str) -> Option<(String, M)> {
+        self.0.remove(k)
+    }
+
+    /// The value for a key
+    pub fn get(&self, k: &str) -> Option<&str> {
+        self.0.get(k).map(|&(ref v, _)| v.as_str())
+    }
+
+    /// The metadata for a key
+    pub fn metadata(&self, k: &str) -> Option<&M> {
+        self.0.get(k).map(|&(_, ref m)| m)
+    }
+
+    /// Iterate over the entries as `(key, value, metadata)`
+    pub fn iter<'a>(&'a self) -> Box<Iterator<Item = (&'a str, &'a str, &'a M)> + 'a> {
+        Box::new(self.0.iter().map(|(k, &(ref v, ref m))| (k.as_str(), v.as_str(), m)))
+    }
+
+    /// Merge `other` into this AnnotatedHstore
+    ///
+    /// Entries from `other` overwrite existing ones along with their metadata, so afterwards the
+    /// metadata of each key tells which side it came from.
+    pub fn merge(&mut self, other: AnnotatedHstore<M>) {
+        self.0.extend(other.0)
+    }
+
+    /// Copy the entries into an Hstore, dropping the metadata
+    pub fn to_hstore(&self) -> Hstore {
+        self.0.iter()
+            .map(|(k, &(ref v, _))| (k.clone(), v.clone()))
+            .collect()
+    }
+
+    /// Convert into an Hstore, dropping the metadata
+    pub fn into_hstore(self) -> Hstore {
+        self.0.into_iter()
+            .map(|(k, (v, _))| (k, v))
+            .collect()
+    }
+}
+
+impl<M> From<AnnotatedHstore<M>> for Hstore {
+    fn from(annotated: AnnotatedHstore<M>) -> Hstore {
+        annotated.into_hstore()
+    }
+}
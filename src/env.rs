@@ -0,0 +1,106 @@
+//! Parsing of `.env` style `KEY=VALUE` lines
+
+use super::Hstore;
+use super::text::HstoreParseError;
+
+impl Hstore {
+    /// Parse newline separated `KEY=VALUE` lines, following the usual `.env` file conventions
+    ///
+    /// * Blank lines and lines starting with `#` are skipped, as is an `export ` prefix.
+    /// * Unquoted values are trimmed and end at a ` #` comment.
+    /// * Single quoted values are taken literally.
+    /// * Double quoted values support the `\n`, `\t`, `\"` and `\\` escapes.
+    ///
+    /// A quoted value must be closed on the same line, and may only be followed by a comment.
+    /// When a key is repeated, the last value wins. Error positions are byte offsets in `s`.
+    ///
+    /// ```rust
+    /// use diesel_pg_hstore::Hstore;
+    ///
+    /// let env = Hstore::from_env_string(r#"
+    /// ## Database settings
+    /// export DB_HOST=localhost
+    /// DB_NAME='app' # the main database
+    /// GREETING="Hello\nWorld"
+    /// "#).unwrap();
+    ///
+    /// assert_eq!(env["DB_HOST"], "localhost");
+    /// assert_eq!(env["DB_NAME"], "app");
+    /// assert_eq!(env["GREETING"], "Hello\nWorld");
+    /// ```
+    pub fn from_env_string(s: &str) -> Result<Hstore, HstoreParseError> {
+        let mut hstore = Hstore::new();
+        let mut offset = 0;
+        for line in s.split('\n') {
+            if let Some((k, v)) = parse_line(line.trim_right_matches('\r'), offset)? {
+                hstore.insert(k, v);
+            }
+            offset += line.len() + 1;
+        }
+        Ok(hstore)
+    }
+}
+
+/// Parse a single line starting at byte `offset` of the input, skipping blanks and comments
+fn parse_line(line: &str, offset: usize) -> Result<Option<(String, String)>, HstoreParseError> {
+    let start = line.len() - line.trim_left().len();
+    let mut rest = &line[start..];
+    if rest.is_empty() || rest.starts_with('#') {
+        return Ok(None);
+    }
+
+    if rest.starts_with("export") && rest[6..].starts_with(|c: char| c.is_whitespace()) {
+        rest = rest[6..].trim_left();
+    }
+    let key_start = offset + line.len() - rest.len();
+
+    let equals = match rest.find('=') {
+        Some(i) => i,
+        None => return Err(HstoreParseError::MissingEquals(offset + line.len())),
+    };
+    let key = rest[..equals].trim();
+    if key.is_empty() {
+        return Err(HstoreParseError::MissingToken(key_start));
+    }
+
+    let value = rest[equals + 1..].trim_left();
+    let value_start = offset + line.len() - value.len();
+    let (value, len) = if value.starts_with('"') {
+        double_quoted(value, value_start)?
+    } else if value.starts_with('\'') {
+        match value[1..].find('\'') {
+            Some(end) => (value[1..end + 1].to_string(), end + 2),
+            None => return Err(HstoreParseError::UnterminatedQuote(value_start)),
+        }
+    } else {
+        let end = value.find(" #").or_else(|| value.find("\t#")).unwrap_or(value.len());
+        (value[..end].trim_right().to_string(), end)
+    };
+
+    let after = value_start + len;
+    let trailing = line[after - offset..].trim_left();
+    if !trailing.is_empty() && !trailing.starts_with('#') {
+        return Err(HstoreParseError::UnexpectedCharacter(offset + line.len() - trailing.len()));
+    }
+
+    Ok(Some((key.to_string(), value)))
+}
+
+/// Read a double quoted value, returning it along with the number of bytes it spans
+fn double_quoted(s: &str, start: usize) -> Result<(String, usize), HstoreParseError> {
+    let mut value = String::new();
+    let mut chars = s.char_indices().skip(1);
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => return Ok((value, i + 1)),
+            '\\' => match chars.next() {
+                Some((_, 'n')) => value.push('\n'),
+                Some((_, 't')) => value.push('\t'),
+                Some((_, escaped)) => value.push(escaped),
+                None => break,
+            },
+            c => value.push(c),
+        }
+    }
+    Err(HstoreParseError::UnterminatedQuote(start))
+}
@@ -0,0 +1,329 @@
+//! Hstore functions for use in Diesel queries
+//!
+//! The Rust names are prefixed with `hstore_` since several hstore functions share an
+//! overloaded SQL name.
+
+use diesel::expression::{AppearsOnTable, AsExpression, Expression, NonAggregate,
+                         SelectableExpression};
+use diesel::pg::Pg;
+use diesel::query_builder::{AstPass, QueryFragment, QueryId};
+use diesel::result::QueryResult;
+use diesel::types::{Array, Bool, Integer, Json, Jsonb, Nullable, Text};
+
+use super::Hstore;
+
+/// Like diesel's `sql_function!`, but with a SQL name distinct from the Rust name
+macro_rules! hstore_function {
+    (
+        $(#[$attr:meta])*
+        fn $fn_name:ident = $sql_name:expr;
+        struct $struct_name:ident($($arg_name:ident: $arg_type:ty),+) -> $return_type:ty;
+    ) => {
+        #[allow(non_camel_case_types)]
+        #[derive(Debug, Clone, Copy)]
+        #[doc(hidden)]
+        pub struct $struct_name<$($arg_name),+> {
+            $($arg_name: $arg_name),+
+        }
+
+        #[allow(non_camel_case_types)]
+        #[doc(hidden)]
+        pub type $fn_name<$($arg_name),+> = $struct_name<$(
+            <$arg_name as ::diesel::expression::AsExpression<$arg_type>>::Expression
+        ),+>;
+
+        $(#[$attr])*
+        #[allow(non_camel_case_types)]
+        pub fn $fn_name<$($arg_name),+>($($arg_name: $arg_name),+) -> $fn_name<$($arg_name),+>
+            where $($arg_name: ::diesel::expression::AsExpression<$arg_type>),+
+        {
+            $struct_name {
+                $($arg_name: $arg_name.as_expression()),+
+            }
+        }
+
+        #[allow(non_camel_case_types)]
+        impl<$($arg_name),+> ::diesel::expression::Expression for $struct_name<$($arg_name),+>
+            where $($arg_name: ::diesel::expression::Expression),+
+        {
+            type SqlType = $return_type;
+        }
+
+        #[allow(non_camel_case_types)]
+        impl<$($arg_name),+> ::diesel::query_builder::QueryFragment<::diesel::pg::Pg>
+            for $struct_name<$($arg_name),+>
+            where $($arg_name: ::diesel::query_builder::QueryFragment<::diesel::pg::Pg>),+
+        {
+            fn walk_ast(&self, mut out: ::diesel::query_builder::AstPass<::diesel::pg::Pg>)
+                -> ::diesel::result::QueryResult<()>
+            {
+                out.push_sql(concat!($sql_name, "("));
+                ::diesel::query_builder::QueryFragment::walk_ast(
+                    &($(&self.$arg_name,)+), out.reborrow())?;
+                out.push_sql(")");
+                Ok(())
+            }
+        }
+
+        #[allow(non_camel_case_types)]
+        impl<$($arg_name),+> ::diesel::query_builder::QueryId for $struct_name<$($arg_name),+>
+            where $($arg_name: ::diesel::query_builder::QueryId),+
+        {
+            type QueryId = $struct_name<$($arg_name::QueryId),+>;
+
+            const HAS_STATIC_QUERY_ID: bool = $($arg_name::HAS_STATIC_QUERY_ID &&)+ true;
+        }
+
+        #[allow(non_camel_case_types)]
+        impl<$($arg_name),+, QS> ::diesel::expression::SelectableExpression<QS>
+            for $struct_name<$($arg_name),+>
+            where $($arg_name: ::diesel::expression::SelectableExpression<QS>,)+
+                  $struct_name<$($arg_name),+>: ::diesel::expression::AppearsOnTable<QS>
+        {
+        }
+
+        #[allow(non_camel_case_types)]
+        impl<$($arg_name),+, QS> ::diesel::expression::AppearsOnTable<QS>
+            for $struct_name<$($arg_name),+>
+            where $($arg_name: ::diesel::expression::AppearsOnTable<QS>,)+
+                  $struct_name<$($arg_name),+>: ::diesel::expression::Expression
+        {
+        }
+
+        #[allow(non_camel_case_types)]
+        impl<$($arg_name),+> ::diesel::expression::NonAggregate for $struct_name<$($arg_name),+>
+            where $($arg_name: ::diesel::expression::NonAggregate,)+
+                  $struct_name<$($arg_name),+>: ::diesel::expression::Expression
+        {
+        }
+    }
+}
+
+hstore_function! {
+    /// Construct a single entry hstore, as in `hstore(key, value)`
+    fn hstore_from_kv = "hstore";
+    struct hstore_from_kv_t(key: Text, value: Text) -> Hstore;
+}
+
+hstore_function! {
+    /// Construct an hstore from a flat array of alternating keys and values, as in
+    /// `hstore(array)`
+    ///
+    /// This is the inverse of the `%%` operator. Postgres fails if the array has an odd length.
+    fn hstore_from_flat_array = "hstore";
+    struct hstore_from_flat_array_t(array: Array<Text>) -> Hstore;
+}
+
+hstore_function! {
+    /// Check if the hstore has the key, even with a null value, as in `exist(hstore, key)`
+    fn hstore_exist = "exist";
+    struct hstore_exist_t(hstore: Hstore, key: Text) -> Bool;
+}
+
+hstore_function! {
+    /// Check if the hstore has a non-null value for the key, as in `defined(hstore, key)`
+    fn hstore_defined = "defined";
+    struct hstore_defined_t(hstore: Hstore, key: Text) -> Bool;
+}
+
+hstore_function! {
+    /// Extract the entries for the given keys, as in `slice(hstore, keys)`
+    ///
+    /// Keys which are absent from the hstore are left out of the result.
+    fn hstore_slice = "slice";
+    struct hstore_slice_t(hstore: Hstore, keys: Array<Text>) -> Hstore;
+}
+
+hstore_function! {
+    /// Delete a key, as in `delete(hstore, key)`
+    fn hstore_delete_key = "delete";
+    struct hstore_delete_key_t(hstore: Hstore, key: Text) -> Hstore;
+}
+
+hstore_function! {
+    /// Delete several keys, as in `delete(hstore, keys)`
+    ///
+    /// Keys which are absent from the hstore are ignored.
+    fn hstore_delete_keys = "delete";
+    struct hstore_delete_keys_t(hstore: Hstore, keys: Array<Text>) -> Hstore;
+}
+
+hstore_function! {
+    /// Delete the entries matching those of `pairs`, as in `delete(hstore, pairs)`
+    ///
+    /// An entry is only deleted when both its key and its value match.
+    fn hstore_delete_matching = "delete";
+    struct hstore_delete_matching_t(hstore: Hstore, pairs: Hstore) -> Hstore;
+}
+
+hstore_function! {
+    /// Return every key as an array, as in `akeys(hstore)`
+    fn hstore_akeys = "akeys";
+    struct hstore_akeys_t(hstore: Hstore) -> Array<Text>;
+}
+
+hstore_function! {
+    /// The length of a text array along `dimension`, as in `array_length(array, dimension)`
+    ///
+    /// The length is null for an empty array. This is used by `key_count` on the result of
+    /// `hstore_akeys`.
+    fn hstore_array_length = "array_length";
+    struct hstore_array_length_t(array: Array<Text>, dimension: Integer) -> Nullable<Integer>;
+}
+
+hstore_function! {
+    /// Replace a null text with `fallback`, as in `coalesce(value, fallback)`
+    ///
+    /// This is used by `get_value_or` on the value for a key.
+    fn hstore_coalesce = "coalesce";
+    struct hstore_coalesce_t(value: Nullable<Text>, fallback: Text) -> Text;
+}
+
+hstore_function! {
+    /// Return every key as a set, as in `skeys(hstore)`
+    ///
+    /// This is a set returning function: selecting it yields one row per entry, so load it into
+    /// a `Vec<String>`. Diesel can not place it in a `FROM` clause, but in the select list each
+    /// source row expands into its keys, in the order Postgres stores them.
+    ///
+    /// ```rust
+    /// # #[macro_use] extern crate diesel;
+    /// # extern crate diesel_pg_hstore;
+    /// use diesel::prelude::*;
+    /// use diesel_pg_hstore::functions::hstore_skeys;
+    ///
+    /// table! {
+    ///     use diesel::types::*;
+    ///     use diesel_pg_hstore::Hstore;
+    ///
+    ///     user_profile {
+    ///         id -> Integer,
+    ///         settings -> Hstore,
+    ///     }
+    /// }
+    ///
+    /// # fn main() {
+    /// use self::user_profile::dsl::*;
+    ///
+    /// let keys = user_profile.select(hstore_skeys(settings)).filter(id.eq(1));
+    /// # }
+    /// ```
+    fn hstore_skeys = "skeys";
+    struct hstore_skeys_t(hstore: Hstore) -> Text;
+}
+
+hstore_function! {
+    /// Return every value as a set, as in `svals(hstore)`
+    ///
+    /// Please see `hstore_skeys` for how to load a set returning function. Unlike `skeys`, null
+    /// values yield a null row, so load this into a `Vec<Option<String>>`.
+    fn hstore_svals = "svals";
+    struct hstore_svals_t(hstore: Hstore) -> Nullable<Text>;
+}
+
+hstore_function! {
+    /// Convert to a `json` object with string or null values, as in `hstore_to_json(hstore)`
+    ///
+    /// Reading the result as a `serde_json::Value` requires the `json` feature. This needs
+    /// version 1.1 of the hstore extension, please see `hstore_extension_version`.
+    fn hstore_to_json = "hstore_to_json";
+    struct hstore_to_json_t(hstore: Hstore) -> Json;
+}
+
+hstore_function! {
+    /// Convert to a `json` object, turning values that look like numbers into numbers and `t` and
+    /// `f` into booleans, as in `hstore_to_json_loose(hstore)`
+    fn hstore_to_json_loose = "hstore_to_json_loose";
+    struct hstore_to_json_loose_t(hstore: Hstore) -> Json;
+}
+
+hstore_function! {
+    /// Convert to a `jsonb` object with string or null values, as in `hstore_to_jsonb(hstore)`
+    ///
+    /// Reading the result as a `serde_json::Value` requires the `json` feature. This needs
+    /// version 1.3 of the hstore extension, please see `hstore_extension_version`.
+    fn hstore_to_jsonb = "hstore_to_jsonb";
+    struct hstore_to_jsonb_t(hstore: Hstore) -> Jsonb;
+}
+
+hstore_function! {
+    /// Convert to a `jsonb` object, turning values that look like numbers into numbers and `t`
+    /// and `f` into booleans, as in `hstore_to_jsonb_loose(hstore)`
+    fn hstore_to_jsonb_loose = "hstore_to_jsonb_loose";
+    struct hstore_to_jsonb_loose_t(hstore: Hstore) -> Jsonb;
+}
+
+/// Expand an hstore into one `(key, value)` row per entry, as in `each(hstore)`
+///
+/// The SQL type is `(Text, Nullable<Text>)`, so the rows load into `(String, Option<String>)`
+/// tuples. Diesel can not place a set returning function in a `FROM` clause, so this is written
+/// `(each(hstore)).*` in the select list, which Postgres expands into the key and value columns.
+/// Each source row then yields one row per entry, and rows with an empty hstore yield none.
+///
+/// To keep track of which row an entry came from, select it along with other columns:
+///
+/// ```rust
+/// # #[macro_use] extern crate diesel;
+/// # extern crate diesel_pg_hstore;
+/// use diesel::prelude::*;
+/// use diesel_pg_hstore::functions::hstore_each;
+///
+/// table! {
+///     use diesel::types::*;
+///     use diesel_pg_hstore::Hstore;
+///
+///     user_profile {
+///         id -> Integer,
+///         settings -> Hstore,
+///     }
+/// }
+///
+/// # fn main() {
+/// use self::user_profile::dsl::*;
+///
+/// // Loads into a `Vec<(i32, (String, Option<String>))>`
+/// let entries = user_profile.select((id, hstore_each(settings)));
+/// # }
+/// ```
+pub fn hstore_each<T: AsExpression<Hstore>>(hstore: T) -> HstoreEach<T::Expression> {
+    HstoreEach { hstore: hstore.as_expression() }
+}
+
+/// The expression returned by `hstore_each`
+#[derive(Debug, Clone, Copy)]
+pub struct HstoreEach<T> {
+    hstore: T,
+}
+
+impl<T: Expression> Expression for HstoreEach<T> {
+    type SqlType = (Text, Nullable<Text>);
+}
+
+impl<T: QueryFragment<Pg>> QueryFragment<Pg> for HstoreEach<T> {
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        out.push_sql("(each(");
+        self.hstore.walk_ast(out.reborrow())?;
+        out.push_sql(")).*");
+        Ok(())
+    }
+}
+
+impl<T: QueryId> QueryId for HstoreEach<T> {
+    type QueryId = HstoreEach<T::QueryId>;
+
+    const HAS_STATIC_QUERY_ID: bool = T::HAS_STATIC_QUERY_ID;
+}
+
+impl<T, QS> SelectableExpression<QS> for HstoreEach<T>
+    where T: SelectableExpression<QS>,
+          HstoreEach<T>: AppearsOnTable<QS>
+{
+}
+
+impl<T, QS> AppearsOnTable<QS> for HstoreEach<T>
+    where T: AppearsOnTable<QS>,
+          HstoreEach<T>: Expression
+{
+}
+
+impl<T: NonAggregate> NonAggregate for HstoreEach<T> where HstoreEach<T>: Expression {}
@@ -0,0 +1,244 @@
+//! Conversions between Hstore and JSON, enabled by the `json` feature
+
+use std::convert::TryFrom;
+use std::error::Error as StdError;
+use std::fmt;
+
+use diesel::expression::{AsExpression, Expression};
+use diesel::expression::bound::Bound;
+use diesel::pg::Pg;
+use diesel::types::Jsonb;
+use serde_json::{Map, Value};
+
+use super::Hstore;
+
+diesel_infix_operator!(JsonbConcat, " || ", Jsonb, backend: Pg);
+
+/// Error returned when a JSON merge patch can not be applied to an Hstore
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MergePatchError {
+    /// The patch is not a JSON object
+    NotAnObject,
+    /// The value for this key is an object or an array, which a flat hstore can not hold
+    NestedValue(String),
+}
+
+impl fmt::Display for MergePatchError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            MergePatchError::NotAnObject => f.write_str(self.description()),
+            MergePatchError::NestedValue(ref key) => {
+                write!(f, "{} for key {:?}", self.description(), key)
+            }
+        }
+    }
+}
+
+impl StdError for MergePatchError {
+    fn description(&self) -> &str {
+        match *self {
+            MergePatchError::NotAnObject => "merge patch is not a JSON object",
+            MergePatchError::NestedValue(_) => "nested value in merge patch",
+        }
+    }
+}
+
+/// Error returned when a JSON value can not be converted into an Hstore
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum JsonConversionError {
+    /// The value is not a JSON object
+    NotAnObject,
+    /// The value for this key is neither a string nor null
+    NonStringValue(String),
+}
+
+impl fmt::Display for JsonConversionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            JsonConversionError::NotAnObject => f.write_str(self.description()),
+            JsonConversionError::NonStringValue(ref key) => {
+                write!(f, "{} for key {:?}", self.description(), key)
+            }
+        }
+    }
+}
+
+impl StdError for JsonConversionError {
+    fn description(&self) -> &str {
+        match *self {
+            JsonConversionError::NotAnObject => "JSON value is not an object",
+            JsonConversionError::NonStringValue(_) => "JSON value is not a string",
+        }
+    }
+}
+
+impl Hstore {
+    /// Create an Hstore from a JSON object with string or null values
+    ///
+    /// Keys set to `null` are left out, as an Hstore can not hold null values. Numbers, booleans,
+    /// arrays and objects are refused rather than converted to text; please see
+    /// `apply_merge_patch` for a looser conversion.
+    ///
+    /// This is the same conversion as `TryFrom<&Value>`.
+    ///
+    /// ```rust
+    /// # #[macro_use] extern crate serde_json;
+    /// # extern crate diesel_pg_hstore;
+    /// use diesel_pg_hstore::Hstore;
+    ///
+    /// # fn main() {
+    /// let settings = Hstore::from_json_value(&json!({"theme": "dark", "font": null})).unwrap();
+    /// assert_eq!(settings.len(), 1);
+    /// assert_eq!(settings["theme"], "dark");
+    /// # }
+    /// ```
+    pub fn from_json_value(value: &Value) -> Result<Hstore, JsonConversionError> {
+        Hstore::try_from(value)
+    }
+
+    /// Convert the Hstore into a JSON object with string values
+    ///
+    /// Values are never coerced, unlike with `hstore_to_json_loose`: `"1"` and `"true"` stay
+    /// JSON strings.
+    ///
+    /// ```rust
+    /// # #[macro_use] extern crate serde_json;
+    /// # extern crate diesel_pg_hstore;
+    /// use diesel_pg_hstore::Hstore;
+    ///
+    /// # fn main() {
+    /// let mut settings = Hstore::new();
+    /// settings.insert("count".into(), "5".into());
+    /// assert_eq!(settings.to_json_value(), json!({"count": "5"}));
+    /// # }
+    /// ```
+    pub fn to_json_value(&self) -> Value {
+        let object: Map<String, Value> = self.iter()
+            .map(|(k, v)| (k.clone(), Value::String(v.clone())))
+            .collect();
+
+        Value::Object(object)
+    }
+
+    /// Bind the Hstore as a `jsonb` object with string values
+    pub fn to_jsonb_expr(&self) -> Bound<Jsonb, Value> {
+        AsExpression::<Jsonb>::as_expression(self.to_json_value())
+    }
+
+    /// Merge the Hstore into a `jsonb` expression, as in `existing || $hstore_as_jsonb`
+    ///
+    /// This is useful for writing hstore shaped data to a `jsonb` column.
+    ///
+    /// ```rust
+    /// # #[macro_use] extern crate diesel;
+    /// # extern crate diesel_pg_hstore;
+    /// use diesel::prelude::*;
+    /// use diesel_pg_hstore::Hstore;
+    ///
+    /// table! {
+    ///     user_profile {
+    ///         id -> Integer,
+    ///         settings -> Jsonb,
+    ///     }
+    /// }
+    ///
+    /// # fn main() {
+    /// use self::user_profile::dsl::*;
+    ///
+    /// let mut changes = Hstore::new();
+    /// changes.insert("theme".into(), "dark".into());
+    ///
+    /// let query = diesel::update(user_profile.filter(id.eq(1)))
+    ///     .set(settings.eq(changes.merge_into_jsonb(settings)));
+    /// # }
+    /// ```
+    pub fn merge_into_jsonb<T>(&self, existing: T) -> JsonbConcat<T, Bound<Jsonb, Value>>
+        where T: Expression<SqlType = Jsonb>
+    {
+        JsonbConcat::new(existing, self.to_jsonb_expr())
+    }
+
+    /// Apply a JSON Merge Patch (RFC 7396) to the Hstore
+    ///
+    /// Keys set to `null` in the patch are removed, and keys set to a string, number or boolean
+    /// are set to its text. Since an hstore is flat, nested objects and arrays are refused. The
+    /// patch is checked before anything is applied, so on error the Hstore is left unchanged.
+    ///
+    /// ```rust
+    /// # #[macro_use] extern crate serde_json;
+    /// # extern crate diesel_pg_hstore;
+    /// use diesel_pg_hstore::Hstore;
+    ///
+    /// # fn main() {
+    /// let mut settings = Hstore::new();
+    /// settings.insert("theme".into(), "light".into());
+    /// settings.insert("beta".into(), "true".into());
+    ///
+    /// settings.apply_merge_patch(&json!({"theme": "dark", "beta": null})).unwrap();
+    /// assert_eq!(settings["theme"], "dark");
+    /// assert!(!settings.contains_key("beta"));
+    /// # }
+    /// ```
+    pub fn apply_merge_patch(&mut self, patch: &Value) -> Result<(), MergePatchError> {
+        let patch = match *patch {
+            Value::Object(ref patch) => patch,
+            _ => return Err(MergePatchError::NotAnObject),
+        };
+
+        for (k, v) in patch {
+            match *v {
+                Value::Object(_) | Value::Array(_) => {
+                    return Err(MergePatchError::NestedValue(k.clone()))
+                }
+                _ => {}
+            }
+        }
+
+        for (k, v) in patch {
+            match *v {
+                Value::Null => {
+                    self.remove(k);
+                }
+                Value::String(ref v) => {
+                    self.insert(k.clone(), v.clone());
+                }
+                ref v => {
+                    self.insert(k.clone(), v.to_string());
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Please see `Hstore::to_json_value`
+impl<'a> From<&'a Hstore> for Value {
+    fn from(hstore: &'a Hstore) -> Value {
+        hstore.to_json_value()
+    }
+}
+
+/// Please see `Hstore::from_json_value`
+impl<'a> TryFrom<&'a Value> for Hstore {
+    type Error = JsonConversionError;
+
+    fn try_from(value: &'a Value) -> Result<Hstore, JsonConversionError> {
+        let object = match *value {
+            Value::Object(ref object) => object,
+            _ => return Err(JsonConversionError::NotAnObject),
+        };
+
+        let mut hstore = Hstore::with_capacity(object.len());
+        for (k, v) in object {
+            match *v {
+                Value::String(ref v) => {
+                    hstore.insert(k.clone(), v.clone());
+                }
+                Value::Null => {}
+                _ => return Err(JsonConversionError::NonStringValue(k.clone())),
+            }
+        }
+        Ok(hstore)
+    }
+}
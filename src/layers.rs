@@ -0,0 +1,94 @@
+//! Resolution of configuration layers applying to different keys
+
+use super::Hstore;
+
+/// Selects the keys a layer applies to, as used by `Hstore::resolve_layers`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeyMatcher {
+    /// Every key
+    Any,
+    /// Keys starting with the prefix
+    Prefix(String),
+    /// Keys matching the pattern, where `*` matches any run of characters, including none, and
+    /// `?` matches a single character. There is no escaping.
+    Glob(String),
+}
+
+impl KeyMatcher {
+    /// Check if the key is selected
+    ///
+    /// ```rust
+    /// use diesel_pg_hstore::KeyMatcher;
+    ///
+    /// assert!(KeyMatcher::Prefix("ui.".into()).matches("ui.theme"));
+    /// assert!(KeyMatcher::Glob("secret.*.key".into()).matches("secret.db.key"));
+    /// assert!(!KeyMatcher::Glob("secret.?".into()).matches("secret.db"));
+    /// ```
+    pub fn matches(&self, key: &str) -> bool {
+        match *self {
+            KeyMatcher::Any => true,
+            KeyMatcher::Prefix(ref prefix) => key.starts_with(prefix.as_str()),
+            KeyMatcher::Glob(ref pattern) => {
+                let pattern: Vec<char> = pattern.chars().collect();
+                let key: Vec<char> = key.chars().collect();
+                glob_matches(&pattern, &key)
+            }
+        }
+    }
+}
+
+/// Match a glob, backtracking to the last `*` on a mismatch
+fn glob_matches(pattern: &[char], key: &[char]) -> bool {
+    let (mut p, mut k) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+
+    while k < key.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == key[k]) {
+            p += 1;
+            k += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, k));
+            p += 1;
+        } else if let Some((star_p, star_k)) = star {
+            p = star_p + 1;
+            k = star_k + 1;
+            star = Some((star_p, star_k + 1));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+impl Hstore {
+    /// Merge layers of entries, each only contributing the keys its matcher selects
+    ///
+    /// Layers are applied in order, so a later layer overrides an earlier one for keys both
+    /// select. Entries a layer's matcher does not select are ignored.
+    ///
+    /// ```rust
+    /// use diesel_pg_hstore::{Hstore, KeyMatcher};
+    ///
+    /// let env = Hstore::from_array([("secret.token", "from-env"), ("ui.theme", "light")]);
+    /// let file = Hstore::from_array([("secret.token", "from-file"), ("ui.theme", "dark")]);
+    ///
+    /// let settings = Hstore::resolve_layers(&[
+    ///     (KeyMatcher::Prefix("secret.".into()), env),
+    ///     (KeyMatcher::Prefix("ui.".into()), file),
+    /// ]);
+    /// assert_eq!(settings["secret.token"], "from-env");
+    /// assert_eq!(settings["ui.theme"], "dark");
+    /// ```
+    pub fn resolve_layers(layers: &[(KeyMatcher, Hstore)]) -> Hstore {
+        let mut resolved = Hstore::new();
+        for &(ref matcher, ref layer) in layers {
+            for (k, v) in layer.iter() {
+                if matcher.matches(k) {
+                    resolved.insert(k.clone(), v.clone());
+                }
+            }
+        }
+        resolved
+    }
+}
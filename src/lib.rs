@@ -91,18 +91,92 @@
 //! things.insert("Hello".into(), "World".into());
 //! ```
 //!
+//! ### Querying hstore columns
+//!
+//! Hstore operators are available on hstore columns once the `HstoreOpExtensions` trait is in
+//! scope. Please see the [predicates](predicates/index.html) module.
+//!
 //! ### Nullable hstore values
 //!
-//! Postgres hstore entries having a null value are simply ignored.
-
+//! Postgres hstore entries having a null value are simply ignored. Declare the column as
+//! `HstoreNullable` instead to keep them as `None`.
+//!
+//! ### Optional features
+//!
+//! * `json`: conversions between Hstore and JSON. Please see the [json](json/index.html) module.
+//! * `unicode`: `Hstore::normalize_unicode`, using the `unicode-normalization` crate.
+//! * `regex`: the `Regex` value type for [shape](shape/index.html) validation.
+//! * `serde`: `Serialize` and `Deserialize` as a plain map, and `Hstore::from_serialize` and
+//!   `Hstore::to_deserialize`, converting between flat structs and an Hstore.
+//! * `indexmap`: the `HstoreOrdered` type, which keeps its entries in insertion order.
+
+#[macro_use]
 extern crate diesel;
 extern crate byteorder;
 extern crate fallible_iterator;
-
+#[cfg(feature = "json")]
+extern crate serde_json;
+#[cfg(feature = "unicode")]
+extern crate unicode_normalization;
+#[cfg(feature = "regex")]
+extern crate regex;
+#[cfg(feature = "serde")]
+#[macro_use]
+extern crate serde;
+#[cfg(feature = "indexmap")]
+extern crate indexmap;
+
+mod annotated;
+mod env;
+pub mod functions;
+mod layers;
+mod matrix;
+#[cfg(feature = "indexmap")]
+mod ordered;
+pub mod predicates;
+pub mod query_string;
+mod record;
+pub mod shape;
+mod strict;
+#[cfg(feature = "serde")]
+mod structs;
+mod text;
+#[cfg(feature = "json")]
+pub mod json;
+#[cfg(feature = "unicode")]
+mod unicode;
+
+pub use annotated::AnnotatedHstore;
+pub use layers::KeyMatcher;
+pub use matrix::TextMatrix;
+#[cfg(feature = "indexmap")]
+pub use ordered::HstoreOrdered;
+pub use predicates::HstoreOpExtensions;
+pub use query_string::QueryStringError;
+pub use record::{FromHstore, HstoreFields, HstoreRecordError};
+pub use shape::{HstoreFlagError, HstoreShape, HstoreShapeError};
+pub use strict::{DuplicateKeyError, EmptyKeyError, EmptyValueError, NonEmptyHstore, StrictHstore};
+#[cfg(feature = "serde")]
+pub use structs::HstoreSerdeError;
+pub use impls::{HstoreDecodeStats, HstoreWireError};
+pub use text::HstoreParseError;
+
+use diesel::pg::PgConnection;
+use diesel::result::QueryResult;
+
+use std::error::Error as StdError;
+use std::fmt;
 use std::ops::{Index, Deref, DerefMut};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::collections::hash_map::*;
+use std::hash::{BuildHasher, Hash, Hasher};
 use std::iter::FromIterator;
+use std::mem;
+use std::str::FromStr;
+use std::sync::Arc;
+
+/// Size above which Postgres starts compressing and moving values out of line, for 8kB pages
+const TOAST_THRESHOLD: usize = 2032;
 
 /// The Hstore wrapper type.
 #[derive(Debug, Clone, Default, PartialEq, Eq)]
@@ -163,6 +237,163 @@ impl Hstore {
         Hstore(hm)
     }
 
+    /// Create a new Hstore from an existing hashmap, checking that it can be sent to Postgres
+    ///
+    /// The wire format prefixes every key and value with its length as a 4 byte signed integer,
+    /// so none may be longer than `i32::MAX` bytes. Encoding an Hstore which was not checked
+    /// fails with the same error.
+    pub fn try_from_hashmap(hm: HashMap<String, String>) -> Result<Hstore, HstoreLengthOverflow> {
+        for (k, v) in &hm {
+            wire_length(k.len())?;
+            wire_length(v.len())?;
+        }
+        Ok(Hstore(hm))
+    }
+
+    /// Create a new Hstore from a hashmap using any hasher
+    ///
+    /// The entries are rehashed into the Hstore's own map.
+    ///
+    /// ```rust
+    /// use diesel_pg_hstore::Hstore;
+    /// use std::collections::HashMap;
+    /// use std::collections::hash_map::DefaultHasher;
+    /// use std::hash::BuildHasherDefault;
+    ///
+    /// let mut settings: HashMap<String, String, BuildHasherDefault<DefaultHasher>> =
+    ///     HashMap::default();
+    /// settings.insert("Hello".into(), "World".into());
+    ///
+    /// let settings_hstore = Hstore::from_hashmap_with_hasher(settings);
+    /// ```
+    pub fn from_hashmap_with_hasher<S>(hm: HashMap<String, String, S>) -> Hstore
+        where S: BuildHasher
+    {
+        hm.into_iter().collect()
+    }
+
+    /// Create a new Hstore from keys and values of any type implementing `Display`
+    ///
+    /// Each value is stored as its `to_string()` representation.
+    ///
+    /// ```rust
+    /// use diesel_pg_hstore::Hstore;
+    ///
+    /// let settings = Hstore::from_display_fields(&[("count", &5), ("on", &true)]);
+    /// assert_eq!(settings["count"], "5");
+    /// assert_eq!(settings["on"], "true");
+    /// ```
+    pub fn from_display_fields<'a>(fields: &[(&'a str, &'a fmt::Display)]) -> Hstore {
+        fields.iter()
+            .map(|&(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    /// Create a new Hstore from a fixed list of pairs, such as a test fixture
+    ///
+    /// The Hstore is allocated with room for every pair up front. When a key is repeated, the
+    /// last value wins.
+    ///
+    /// ```rust
+    /// use diesel_pg_hstore::Hstore;
+    ///
+    /// let settings = Hstore::from_array([("a", "1"), ("b", "2")]);
+    /// assert_eq!(settings.len(), 2);
+    /// assert_eq!(settings["b"], "2");
+    /// ```
+    pub fn from_array<const N: usize>(pairs: [(&str, &str); N]) -> Hstore {
+        let mut hstore = Hstore::with_capacity(N);
+        for &(k, v) in pairs.iter() {
+            hstore.insert(k.to_string(), v.to_string());
+        }
+        hstore
+    }
+
+    /// Create a new Hstore from fallible entries, stopping at the first error
+    ///
+    /// ```rust
+    /// use diesel_pg_hstore::Hstore;
+    ///
+    /// let lines = vec!["a=1", "b=2"];
+    /// let settings = Hstore::try_from_iter(lines.iter().map(|line| {
+    ///     let mut parts = line.splitn(2, '=');
+    ///     match (parts.next(), parts.next()) {
+    ///         (Some(k), Some(v)) => Ok((k.to_string(), v.to_string())),
+    ///         _ => Err(format!("invalid line {:?}", line)),
+    ///     }
+    /// })).unwrap();
+    /// assert_eq!(settings["b"], "2");
+    /// ```
+    pub fn try_from_iter<E, I>(iter: I) -> Result<Hstore, E>
+        where I: IntoIterator<Item = Result<(String, String), E>>
+    {
+        iter.into_iter().collect::<Result<HashMap<_, _>, E>>().map(Hstore)
+    }
+
+    /// Create a new Hstore from alternating keys and values, as produced by the `%%` operator
+    ///
+    /// Fails if there is an odd number of strings. When a key is repeated, the last value wins.
+    ///
+    /// ```rust
+    /// use diesel_pg_hstore::Hstore;
+    ///
+    /// let flat = vec!["a".to_string(), "1".to_string(), "b".to_string(), "2".to_string()];
+    /// let settings = Hstore::from_flat_strings(&flat).unwrap();
+    /// assert_eq!(settings["b"], "2");
+    /// ```
+    pub fn from_flat_strings(v: &[String]) -> Result<Hstore, HstoreLenMismatch> {
+        if v.len() % 2 != 0 {
+            return Err(HstoreLenMismatch { len: v.len() });
+        }
+
+        Ok(v.chunks(2)
+            .map(|pair| (pair[0].clone(), pair[1].clone()))
+            .collect())
+    }
+
+    /// Flatten into alternating keys and values, sorted by key, like the `%%` operator
+    pub fn to_flat_strings(&self) -> Vec<String> {
+        let entries = self.entries_by_key();
+
+        let mut flat = Vec::with_capacity(entries.len() * 2);
+        for (k, v) in entries {
+            flat.push(k.clone());
+            flat.push(v.clone());
+        }
+        flat
+    }
+
+    /// Create a new Hstore from a list of key and value pairs, as sent by some APIs
+    ///
+    /// When a key is repeated in the list, the last value wins.
+    pub fn from_kv_list(list: &[KeyValue]) -> Hstore {
+        list.iter()
+            .map(|kv| (kv.key.clone(), kv.value.clone()))
+            .collect()
+    }
+
+    /// Convert into a list of key and value pairs, sorted by key
+    ///
+    /// With the `serde` feature, this serializes to `[{"key": "a", "value": "1"}]`, which has a
+    /// stable shape unlike an object with dynamic keys.
+    ///
+    /// ```rust
+    /// use diesel_pg_hstore::{Hstore, KeyValue};
+    ///
+    /// let mut settings = Hstore::new();
+    /// settings.insert("a".into(), "1".into());
+    ///
+    /// let list = settings.to_kv_list();
+    /// assert_eq!(list, vec![KeyValue { key: "a".into(), value: "1".into() }]);
+    /// assert_eq!(Hstore::from_kv_list(&list), settings);
+    /// ```
+    pub fn to_kv_list(&self) -> Vec<KeyValue> {
+        self.entries_by_key()
+            .into_iter()
+            .map(|(k, v)| KeyValue { key: k.clone(), value: v.clone() })
+            .collect()
+    }
+
     /// Please see [HashMap.with_capacity](https://doc.rust-lang.org/std/collections/struct.HashMap.html#method.with_capacity)
     pub fn with_capacity(capacity: usize) -> Hstore {
         Hstore(HashMap::with_capacity(capacity))
@@ -213,6 +444,37 @@ impl Hstore {
         self.0.entry(key)
     }
 
+    /// Like `entry`, but looks the key up by reference
+    ///
+    /// The key is only copied into an owned `String` when a vacant entry is inserted into, which
+    /// avoids an allocation per call when polling keys that are usually present. A present key is
+    /// looked up once, and the entry keeps the value it found.
+    ///
+    /// The key only has to outlive the entry, not the values borrowed from it.
+    ///
+    /// ```rust
+    /// use diesel_pg_hstore::Hstore;
+    ///
+    /// let mut settings = Hstore::new();
+    /// settings.entry_str("Hello").or_insert("World".into());
+    /// settings.entry_str("Hello").or_insert("Again".into()).push('!');
+    ///
+    /// assert_eq!(settings["Hello"], "World!");
+    /// ```
+    pub fn entry_str<'a, 'k>(&'a mut self, key: &'k str) -> HstoreEntry<'a, 'k> {
+        let map: *mut HashMap<String, String> = &mut self.0;
+
+        // The borrow checker does not see that the value is only kept when the lookup succeeds,
+        // so the map is reached through a pointer. Either the value or the map is used from here
+        // on, never both at once.
+        match unsafe { (*map).get_mut(key) } {
+            Some(value) => {
+                HstoreEntry::Occupied(HstoreOccupiedEntry { map: map, value: value, key: key })
+            }
+            None => HstoreEntry::Vacant(HstoreVacantEntry { map: unsafe { &mut *map }, key: key }),
+        }
+    }
+
     /// Please see [HashMap.len](#method.len-1)
     pub fn len(&self) -> usize {
         self.0.len()
@@ -253,6 +515,24 @@ impl Hstore {
         self.0.insert(k, v)
     }
 
+    /// Like `insert`, copying borrowed strings into the Hstore
+    ///
+    /// ```rust
+    /// use diesel_pg_hstore::Hstore;
+    ///
+    /// let mut settings = Hstore::new();
+    /// assert_eq!(settings.insert_str("Hello", "World"), None);
+    /// assert_eq!(settings.insert_str("Hello", "There"), Some("World".into()));
+    /// ```
+    pub fn insert_str(&mut self, k: &str, v: &str) -> Option<String> {
+        self.0.insert(k.to_string(), v.to_string())
+    }
+
+    /// Like `insert`, converting the key and value into owned strings
+    pub fn insert_any<K: Into<String>, V: Into<String>>(&mut self, k: K, v: V) -> Option<String> {
+        self.0.insert(k.into(), v.into())
+    }
+
     /// Please see [HashMap.remove](#method.remove-1)
     pub fn remove(&mut self, k: &str) -> Option<String> {
         self.0.remove(k)
@@ -264,127 +544,1099 @@ impl Hstore {
     {
         self.0.retain(f)
     }
-}
 
-impl IntoIterator for Hstore {
-    type Item = (String, String);
-    type IntoIter = IntoIter<String, String>;
+    /// Split the value for `key` on `sep`, for keys holding several values
+    ///
+    /// An absent key and an empty value both give no values. There is no escaping: a value
+    /// inserted with `insert_multi` which contains `sep` comes back split in several values.
+    ///
+    /// ```rust
+    /// use diesel_pg_hstore::Hstore;
+    ///
+    /// let mut settings = Hstore::new();
+    /// settings.insert_multi("tags", &["red", "blue"], '|');
+    /// assert_eq!(settings["tags"], "red|blue");
+    /// assert_eq!(settings.get_multi("tags", '|'), vec!["red", "blue"]);
+    /// ```
+    pub fn get_multi(&self, key: &str, sep: char) -> Vec<&str> {
+        match self.0.get(key) {
+            Some(v) if !v.is_empty() => v.split(sep).collect(),
+            _ => Vec::new(),
+        }
+    }
 
-    fn into_iter(self) -> Self::IntoIter {
-        self.0.into_iter()
+    /// Join `values` with `sep` and store them under `key`, replacing any previous value
+    ///
+    /// Please see `get_multi` for the caveats of this convention.
+    pub fn insert_multi(&mut self, key: &str, values: &[&str], sep: char) {
+        let mut joined = String::new();
+        for (i, v) in values.iter().enumerate() {
+            if i > 0 {
+                joined.push(sep);
+            }
+            joined.push_str(v);
+        }
+        self.0.insert(key.to_string(), joined);
     }
-}
 
-impl<'a> IntoIterator for &'a Hstore {
-    type Item = (&'a String, &'a String);
-    type IntoIter = Iter<'a, String, String>;
+    /// The total UTF-8 byte length of all keys and values
+    ///
+    /// This does not account for the length prefixes used on the wire.
+    ///
+    /// ```rust
+    /// use diesel_pg_hstore::Hstore;
+    ///
+    /// let mut settings = Hstore::new();
+    /// settings.insert("Hello".into(), "World".into());
+    /// assert_eq!(settings.content_bytes(), 10);
+    /// ```
+    pub fn content_bytes(&self) -> usize {
+        self.0.iter().map(|(k, v)| k.len() + v.len()).sum()
+    }
 
-    fn into_iter(self) -> Self::IntoIter {
-        self.iter()
+    /// Compute the patch turning `old` into `new`, along with the patch undoing it
+    ///
+    /// Since the hstore `||` operator can not express deletions, each patch carries the keys to
+    /// remove separately from the entries to set. Please see `HstorePatch`.
+    ///
+    /// ```rust
+    /// use diesel_pg_hstore::Hstore;
+    ///
+    /// let mut old = Hstore::new();
+    /// old.insert("a".into(), "1".into());
+    /// let mut new = Hstore::new();
+    /// new.insert("b".into(), "2".into());
+    ///
+    /// let (patch, inverse) = Hstore::patch_and_inverse(&old, &new);
+    /// let mut edited = old.clone();
+    /// patch.apply(&mut edited);
+    /// assert_eq!(edited, new);
+    /// inverse.apply(&mut edited);
+    /// assert_eq!(edited, old);
+    /// ```
+    pub fn patch_and_inverse(old: &Hstore, new: &Hstore) -> (HstorePatch, HstorePatch) {
+        (HstorePatch::between(old, new), HstorePatch::between(new, old))
     }
-}
 
-impl<'a> IntoIterator for &'a mut Hstore {
-    type Item = (&'a String, &'a mut String);
-    type IntoIter = IterMut<'a, String, String>;
+    /// The size in bytes of the Hstore in the Postgres binary wire format
+    ///
+    /// Unlike `content_bytes`, this includes the entry count and the length prefix of every key
+    /// and value.
+    pub fn wire_size(&self) -> usize {
+        4 + self.0.iter().map(|(k, v)| 8 + k.len() + v.len()).sum::<usize>()
+    }
 
-    fn into_iter(self) -> Self::IntoIter {
-        self.iter_mut()
+    /// Guess whether Postgres would move the Hstore out of line into TOAST storage
+    ///
+    /// This is a rough approximation: it compares `wire_size` against the default TOAST threshold
+    /// of about 2kB, ignoring the rest of the row and compression. It is meant for capacity
+    /// planning, not as a statement about how a value is actually stored.
+    pub fn likely_toasted(&self) -> bool {
+        self.wire_size() > TOAST_THRESHOLD
     }
-}
 
-impl FromIterator<(String, String)> for Hstore {
-    fn from_iter<T>(iter: T) -> Hstore
-        where T: IntoIterator<Item = (String, String)>
-    {
-        Hstore(HashMap::from_iter(iter))
+    /// The keys as a set of borrowed strings
+    ///
+    /// ```rust
+    /// use diesel_pg_hstore::Hstore;
+    /// use std::collections::HashSet;
+    ///
+    /// let mut settings = Hstore::new();
+    /// settings.insert("a".into(), "1".into());
+    /// settings.insert("b".into(), "2".into());
+    ///
+    /// let wanted: HashSet<&str> = ["b", "c"].iter().cloned().collect();
+    /// let common: Vec<&str> = settings.key_set().intersection(&wanted).cloned().collect();
+    /// assert_eq!(common, vec!["b"]);
+    /// ```
+    pub fn key_set(&self) -> HashSet<&str> {
+        self.0.keys().map(|k| k.as_str()).collect()
     }
-}
 
-impl<'a> Index<&'a str> for Hstore {
-    type Output = String;
+    /// Check if every entry of `other` is present in the Hstore, like the `@>` operator
+    pub fn is_superset(&self, other: &Hstore) -> bool {
+        other.iter().all(|(k, v)| self.get(k) == Some(v))
+    }
 
-    #[inline]
-    fn index(&self, index: &'a str) -> &Self::Output {
-        self.0.get(index).expect("no entry found for key")
+    /// Like `is_superset`, but comparing values without regard to case
+    ///
+    /// Keys must still match exactly. Values are compared after `to_lowercase`, so this follows
+    /// Unicode case rules rather than only ASCII ones.
+    ///
+    /// ```rust
+    /// use diesel_pg_hstore::Hstore;
+    ///
+    /// let mut settings = Hstore::new();
+    /// settings.insert("theme".into(), "Dark".into());
+    /// let mut wanted = Hstore::new();
+    /// wanted.insert("theme".into(), "dark".into());
+    ///
+    /// assert!(!settings.is_superset(&wanted));
+    /// assert!(settings.contains_ignore_case(&wanted));
+    /// ```
+    pub fn contains_ignore_case(&self, other: &Hstore) -> bool {
+        other.iter().all(|(k, v)| match self.get(k) {
+            Some(mine) => mine == v || mine.to_lowercase() == v.to_lowercase(),
+            None => false,
+        })
     }
-}
 
-impl Extend<(String, String)> for Hstore {
-    fn extend<T>(&mut self, iter: T)
-        where T: IntoIterator<Item = (String, String)>
-    {
-        self.0.extend(iter)
+    /// The keys from `required` which are absent from the Hstore, in the order given
+    ///
+    /// ```rust
+    /// use diesel_pg_hstore::Hstore;
+    ///
+    /// let mut settings = Hstore::new();
+    /// settings.insert("host".into(), "localhost".into());
+    ///
+    /// assert_eq!(settings.missing_keys(&["host", "port", "user"]), vec!["port", "user"]);
+    /// ```
+    pub fn missing_keys<'a>(&self, required: &[&'a str]) -> Vec<&'a str> {
+        required.iter()
+            .filter(|k| !self.0.contains_key(**k))
+            .cloned()
+            .collect()
     }
-}
 
-mod impls {
-    use std::str;
-    use std::error::Error as StdError;
-    use std::io::Write;
-    use std::collections::HashMap;
-    use fallible_iterator::FallibleIterator;
-    use byteorder::{ReadBytesExt, WriteBytesExt, BigEndian};
-    use diesel::types::impls::option::UnexpectedNullError;
-    use diesel::Queryable;
-    use diesel::expression::AsExpression;
-    use diesel::expression::bound::Bound;
-    use diesel::pg::Pg;
-    use diesel::row::Row;
-    use diesel::types::*;
+    /// The entries whose value fails to parse as a `T`, sorted by key
+    ///
+    /// This checks every value in one pass, instead of finding the failures one at a time when
+    /// reading them.
+    ///
+    /// ```rust
+    /// use diesel_pg_hstore::Hstore;
+    ///
+    /// let limits = Hstore::from_array([("retries", "3"), ("timeout", "soon")]);
+    /// let bad = limits.unparseable_values::<i64>();
+    /// assert_eq!(bad, vec![(&"timeout".to_string(), &"soon".to_string())]);
+    /// ```
+    pub fn unparseable_values<T: FromStr>(&self) -> Vec<(&String, &String)> {
+        let mut entries: Vec<(&String, &String)> = self.0.iter()
+            .filter(|&(_, v)| v.parse::<T>().is_err())
+            .collect();
+        entries.sort();
+        entries
+    }
 
-    use super::Hstore;
+    /// The entries sorted by key
+    ///
+    /// Iterating over an Hstore visits the entries in an arbitrary order, which also differs from
+    /// the order Postgres keeps them in. This gives a stable order, such as for comparing output
+    /// in tests.
+    ///
+    /// ```rust
+    /// use diesel_pg_hstore::Hstore;
+    ///
+    /// let mut settings = Hstore::new();
+    /// settings.insert("b".into(), "1".into());
+    /// settings.insert("a".into(), "2".into());
+    ///
+    /// let keys: Vec<&String> = settings.entries_by_key().into_iter().map(|(k, _)| k).collect();
+    /// assert_eq!(keys, vec!["a", "b"]);
+    /// ```
+    pub fn entries_by_key(&self) -> Vec<(&String, &String)> {
+        let mut entries: Vec<(&String, &String)> = self.0.iter().collect();
+        entries.sort();
+        entries
+    }
 
-    impl HasSqlType<Hstore> for Pg {
-        fn metadata(lookup: &Self::MetadataLookup) -> Self::TypeMetadata {
-            lookup.lookup_type("hstore")
-        }
+    /// Convert into owned entries sorted by key, as with `entries_by_key`
+    ///
+    /// ```rust
+    /// use diesel_pg_hstore::Hstore;
+    ///
+    /// let settings = Hstore::from_array([("b", "1"), ("c", "3"), ("a", "2")]);
+    /// assert_eq!(settings.into_sorted_vec(), vec![
+    ///     ("a".to_string(), "2".to_string()),
+    ///     ("b".to_string(), "1".to_string()),
+    ///     ("c".to_string(), "3".to_string()),
+    /// ]);
+    /// ```
+    pub fn into_sorted_vec(self) -> Vec<(String, String)> {
+        let mut entries: Vec<(String, String)> = self.0.into_iter().collect();
+        entries.sort();
+        entries
     }
 
-    impl NotNull for Hstore {}
-    impl SingleValue for Hstore {}
-    impl Queryable<Hstore, Pg> for Hstore {
-        type Row = Self;
+    /// The entries sorted by value, then by key for entries sharing a value
+    ///
+    /// ```rust
+    /// use diesel_pg_hstore::Hstore;
+    ///
+    /// let mut scores = Hstore::new();
+    /// scores.insert("b".into(), "2".into());
+    /// scores.insert("a".into(), "3".into());
+    /// scores.insert("c".into(), "1".into());
+    ///
+    /// let keys: Vec<&String> = scores.entries_by_value().into_iter().map(|(k, _)| k).collect();
+    /// assert_eq!(keys, vec!["c", "b", "a"]);
+    /// ```
+    pub fn entries_by_value(&self) -> Vec<(&String, &String)> {
+        let mut entries: Vec<(&String, &String)> = self.0.iter().collect();
+        entries.sort_by(|&(ka, va), &(kb, vb)| (va, ka).cmp(&(vb, kb)));
+        entries
+    }
 
-        fn build(row: Self::Row) -> Self {
-            row
+    /// Partition the entries into Hstores keyed by `f(key)`
+    ///
+    /// ```rust
+    /// use diesel_pg_hstore::Hstore;
+    ///
+    /// let mut settings = Hstore::new();
+    /// settings.insert("ui.theme".into(), "dark".into());
+    /// settings.insert("db.pool".into(), "5".into());
+    ///
+    /// let groups = settings.group_by_key(|k| k.split('.').next().unwrap().to_string());
+    /// assert_eq!(groups["ui"]["ui.theme"], "dark");
+    /// assert_eq!(groups["db"]["db.pool"], "5");
+    /// ```
+    pub fn group_by_key<K, F>(&self, f: F) -> HashMap<K, Hstore>
+        where K: Eq + Hash,
+              F: Fn(&str) -> K
+    {
+        let mut groups: HashMap<K, Hstore> = HashMap::new();
+        for (k, v) in &self.0 {
+            groups.entry(f(k))
+                .or_insert_with(Hstore::new)
+                .insert(k.clone(), v.clone());
         }
+        groups
     }
 
-    impl<'a> AsExpression<Hstore> for &'a Hstore {
-        type Expression = Bound<Hstore, &'a Hstore>;
-
-        fn as_expression(self) -> Self::Expression {
-            Bound::new(self)
+    /// Split the entries on the first `sep` of each key into Hstores keyed by namespace
+    ///
+    /// The entries of each namespace are keyed by the rest of the key, after the separator. Keys
+    /// starting with the separator belong to the namespace `""`.
+    ///
+    /// Keys without the separator go into a default group under their full key. That group is
+    /// keyed by the separator itself, such as `"."`, which no namespace can be equal to as
+    /// namespaces never contain the separator. So `"a"` and `".a"` do not collide.
+    ///
+    /// ```rust
+    /// use diesel_pg_hstore::Hstore;
+    ///
+    /// let mut settings = Hstore::new();
+    /// settings.insert("ui.theme".into(), "dark".into());
+    /// settings.insert("db.pool.size".into(), "5".into());
+    /// settings.insert("debug".into(), "true".into());
+    ///
+    /// let namespaces = settings.split_namespaces('.');
+    /// assert_eq!(namespaces["ui"]["theme"], "dark");
+    /// assert_eq!(namespaces["db"]["pool.size"], "5");
+    /// assert_eq!(namespaces["."]["debug"], "true");
+    /// ```
+    pub fn split_namespaces(&self, sep: char) -> HashMap<String, Hstore> {
+        let mut namespaces: HashMap<String, Hstore> = HashMap::new();
+        for (k, v) in &self.0 {
+            let (namespace, rest) = match k.find(sep) {
+                Some(i) => (k[..i].to_string(), &k[i + sep.len_utf8()..]),
+                None => (sep.to_string(), k.as_str()),
+            };
+            namespaces.entry(namespace)
+                .or_insert_with(Hstore::new)
+                .insert(rest.to_string(), v.clone());
         }
+        namespaces
     }
 
-    impl FromSql<Hstore, Pg> for Hstore {
-        fn from_sql(bytes: Option<&[u8]>) -> Result<Self, Box<StdError + Send + Sync>> {
-            let mut buf = match bytes {
-                Some(bytes) => bytes,
-                None => return Err(Box::new(UnexpectedNullError {
-                    msg: "Unexpected null for non-null column".to_string(),
-                })),
-            };
-            let count = buf.read_i32::<BigEndian>()?;
+    /// Merge `other` into the Hstore, overwriting existing keys
+    ///
+    /// This is the `||` operator of Postgres, without a round trip to the database.
+    pub fn merge(&mut self, other: Hstore) {
+        self.0.extend(other.0);
+    }
+
+    /// Merge `other` into the Hstore and return the result, as with `merge`
+    ///
+    /// ```rust
+    /// use diesel_pg_hstore::Hstore;
+    ///
+    /// let defaults = Hstore::from_array([("theme", "light"), ("lang", "en")]);
+    /// let user = Hstore::from_array([("theme", "dark")]);
+    ///
+    /// let settings = defaults.merged(user);
+    /// assert_eq!(settings, Hstore::from_array([("theme", "dark"), ("lang", "en")]));
+    /// ```
+    pub fn merged(mut self, other: Hstore) -> Hstore {
+        self.merge(other);
+        self
+    }
+
+    /// Merge `other` into the Hstore, letting `keep_other` pick the value for conflicting keys
+    ///
+    /// Keys only present in `other` are always inserted. For keys present in both, `keep_other`
+    /// is called with the key, the current value and the value from `other`, and returns whether
+    /// to take the value from `other`.
+    ///
+    /// ```rust
+    /// use diesel_pg_hstore::Hstore;
+    ///
+    /// let mut settings = Hstore::new();
+    /// settings.insert("retries".into(), "3".into());
+    /// let mut update = Hstore::new();
+    /// update.insert("retries".into(), "5".into());
+    ///
+    /// // Only ever raise the number of retries
+    /// settings.merge_with(update, |_, old, new| {
+    ///     new.parse::<u32>().unwrap() > old.parse::<u32>().unwrap()
+    /// });
+    /// assert_eq!(settings["retries"], "5");
+    /// ```
+    pub fn merge_with<F>(&mut self, other: Hstore, mut keep_other: F)
+        where F: FnMut(&str, &str, &str) -> bool
+    {
+        for (k, v) in other.0 {
+            match self.0.entry(k) {
+                Entry::Occupied(mut e) => {
+                    if keep_other(e.key(), e.get(), &v) {
+                        e.insert(v);
+                    }
+                }
+                Entry::Vacant(e) => {
+                    e.insert(v);
+                }
+            }
+        }
+    }
+
+    /// Merge `other` into the Hstore, overwriting existing keys, and report what was overwritten
+    ///
+    /// The overrides are sorted by key. A key is reported even if its value did not change.
+    ///
+    /// ```rust
+    /// use diesel_pg_hstore::Hstore;
+    ///
+    /// let mut settings = Hstore::new();
+    /// settings.insert("theme".into(), "light".into());
+    /// let mut user = Hstore::new();
+    /// user.insert("theme".into(), "dark".into());
+    /// user.insert("lang".into(), "en".into());
+    ///
+    /// let overrides = settings.merge_logging(user);
+    /// assert_eq!(overrides.len(), 1);
+    /// assert_eq!(overrides[0].key, "theme");
+    /// assert_eq!(overrides[0].old, "light");
+    /// assert_eq!(overrides[0].new, "dark");
+    /// ```
+    pub fn merge_logging(&mut self, other: Hstore) -> Vec<KeyOverride> {
+        let mut overrides = Vec::new();
+        for (k, v) in other.0 {
+            if let Some(old) = self.0.insert(k.clone(), v.clone()) {
+                overrides.push(KeyOverride {
+                    key: k,
+                    old: old,
+                    new: v,
+                });
+            }
+        }
+        overrides.sort_by(|a, b| a.key.cmp(&b.key));
+        overrides
+    }
+
+    /// Move the entries into a reference counted map, for cheap sharing between readers
+    ///
+    /// The entries are not copied, and clones of the returned `Arc` share them. The shared map
+    /// is read only: to change the settings, build a new Hstore and share that instead.
+    ///
+    /// ```rust
+    /// use diesel_pg_hstore::Hstore;
+    /// use std::sync::Arc;
+    ///
+    /// let mut settings = Hstore::new();
+    /// settings.insert("Hello".into(), "World".into());
+    ///
+    /// let shared = settings.into_arc();
+    /// let handler = Arc::clone(&shared);
+    /// assert!(Arc::ptr_eq(&shared, &handler));
+    /// ```
+    pub fn into_arc(self) -> Arc<HashMap<String, String>> {
+        Arc::new(self.0)
+    }
+
+    /// Trim surrounding whitespace from every value
+    ///
+    /// Keys are left untouched; please see `collapse_whitespace_keys` for those.
+    pub fn trim_values(&mut self) {
+        for v in self.0.values_mut() {
+            let trimmed = v.trim();
+            if trimmed.len() != v.len() {
+                *v = trimmed.to_string();
+            }
+        }
+    }
+
+    /// A copy of the Hstore with surrounding whitespace trimmed from every value
+    ///
+    /// Keys are left untouched, as with `trim_values`.
+    ///
+    /// ```rust
+    /// use diesel_pg_hstore::Hstore;
+    ///
+    /// let mut settings = Hstore::new();
+    /// settings.insert(" key ".into(), "  value\n".into());
+    ///
+    /// assert_eq!(settings.trimmed_values()[" key "], "value");
+    /// ```
+    pub fn trimmed_values(&self) -> Hstore {
+        self.0.iter()
+            .map(|(k, v)| (k.clone(), v.trim().to_string()))
+            .collect()
+    }
+
+    /// Pairs of distinct keys which are equal once surrounding whitespace is trimmed
+    ///
+    /// Each pair is ordered, and the pairs are sorted.
+    ///
+    /// ```rust
+    /// use diesel_pg_hstore::Hstore;
+    ///
+    /// let mut settings = Hstore::new();
+    /// settings.insert("key".into(), "1".into());
+    /// settings.insert("key ".into(), "2".into());
+    ///
+    /// assert_eq!(settings.whitespace_key_issues(), vec![("key".to_string(), "key ".to_string())]);
+    /// ```
+    pub fn whitespace_key_issues(&self) -> Vec<(String, String)> {
+        let mut issues = Vec::new();
+        for keys in self.whitespace_key_groups().values() {
+            for (i, a) in keys.iter().enumerate() {
+                for b in &keys[i + 1..] {
+                    issues.push((a.to_string(), b.to_string()));
+                }
+            }
+        }
+        issues.sort();
+        issues
+    }
+
+    /// Merge keys which are equal once trimmed into a single entry under the trimmed key
+    ///
+    /// Only keys colliding with another key are affected. When `prefer_trimmed` is set and one of
+    /// the colliding keys is already trimmed, its value is kept. Otherwise the value of the
+    /// smallest untrimmed key, in byte order, is kept.
+    pub fn collapse_whitespace_keys(&mut self, prefer_trimmed: bool) {
+        let groups: Vec<Vec<String>> = self.whitespace_key_groups()
+            .into_iter()
+            .map(|(_, keys)| keys.into_iter().map(String::from).collect())
+            .collect();
+
+        for keys in groups {
+            let trimmed = keys[0].trim().to_string();
+            let winner = if prefer_trimmed && self.0.contains_key(&trimmed) {
+                trimmed.clone()
+            } else {
+                keys.iter()
+                    .find(|k| **k != trimmed)
+                    .cloned()
+                    .expect("colliding keys to include an untrimmed one")
+            };
+
+            let value = self.0.remove(&winner).expect("key to be present");
+            for k in &keys {
+                self.0.remove(k);
+            }
+            self.0.insert(trimmed, value);
+        }
+    }
+
+    /// Keys sharing their trimmed form with another key, grouped by trimmed form and sorted
+    fn whitespace_key_groups(&self) -> HashMap<&str, Vec<&str>> {
+        let mut groups: HashMap<&str, Vec<&str>> = HashMap::new();
+        for k in self.0.keys() {
+            groups.entry(k.trim()).or_insert_with(Vec::new).push(k);
+        }
+        groups.retain(|_, keys| keys.len() > 1);
+        for keys in groups.values_mut() {
+            keys.sort();
+        }
+        groups
+    }
+
+    /// Create an HstoreNullable where every empty string value is stored as a null
+    ///
+    /// ```rust
+    /// use diesel_pg_hstore::Hstore;
+    ///
+    /// let mut settings = Hstore::new();
+    /// settings.insert("Hello".into(), "World".into());
+    /// settings.insert("Empty".into(), "".into());
+    ///
+    /// let nullable = settings.with_empty_as_null();
+    /// assert_eq!(nullable["Hello"], Some("World".to_string()));
+    /// assert_eq!(nullable["Empty"], None);
+    /// ```
+    pub fn with_empty_as_null(&self) -> HstoreNullable {
+        self.0.iter()
+            .map(|(k, v)| {
+                let v = if v.is_empty() { None } else { Some(v.clone()) };
+                (k.clone(), v)
+            })
+            .collect()
+    }
+}
+
+impl IntoIterator for Hstore {
+    type Item = (String, String);
+    type IntoIter = IntoIter<String, String>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a Hstore {
+    type Item = (&'a String, &'a String);
+    type IntoIter = Iter<'a, String, String>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a mut Hstore {
+    type Item = (&'a String, &'a mut String);
+    type IntoIter = IterMut<'a, String, String>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+impl FromIterator<(String, String)> for Hstore {
+    fn from_iter<T>(iter: T) -> Hstore
+        where T: IntoIterator<Item = (String, String)>
+    {
+        Hstore(HashMap::from_iter(iter))
+    }
+}
+
+impl From<HashMap<String, String>> for Hstore {
+    fn from(hm: HashMap<String, String>) -> Hstore {
+        Hstore(hm)
+    }
+}
+
+impl From<Hstore> for HashMap<String, String> {
+    fn from(hstore: Hstore) -> HashMap<String, String> {
+        hstore.0
+    }
+}
+
+/// Arrays of up to 32 entries convert the same way
+///
+/// ```rust
+/// use diesel_pg_hstore::Hstore;
+///
+/// let settings = Hstore::from(vec![("Hello".to_string(), "World".to_string())]);
+/// assert_eq!(settings["Hello"], "World");
+///
+/// let settings = Hstore::from([("Hello".into(), "World".into()), ("a".into(), "b".into())]);
+/// assert_eq!(settings["a"], "b");
+/// ```
+impl From<Vec<(String, String)>> for Hstore {
+    fn from(entries: Vec<(String, String)>) -> Hstore {
+        entries.into_iter().collect()
+    }
+}
+
+/// The keys and values are copied into owned strings
+///
+/// ```rust
+/// use diesel_pg_hstore::Hstore;
+///
+/// let entries: &[(&str, &str)] = &[("Hello", "World")];
+/// let settings = Hstore::from(entries);
+/// assert_eq!(settings["Hello"], "World");
+/// ```
+impl<'a, 'b> From<&'a [(&'b str, &'b str)]> for Hstore {
+    fn from(entries: &'a [(&'b str, &'b str)]) -> Hstore {
+        entries.iter().map(|&(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+}
+
+macro_rules! array_impls {
+    ($($n:expr)*) => {
+        $(
+            /// The strings are moved out of the array, not copied
+            impl From<[(String, String); $n]> for Hstore {
+                fn from(mut entries: [(String, String); $n]) -> Hstore {
+                    entries.iter_mut()
+                        .map(|&mut (ref mut k, ref mut v)| {
+                            (mem::replace(k, String::new()), mem::replace(v, String::new()))
+                        })
+                        .collect()
+                }
+            }
+        )*
+    }
+}
+
+array_impls! {
+    0 1 2 3 4 5 6 7 8 9 10 11 12 13 14 15 16
+    17 18 19 20 21 22 23 24 25 26 27 28 29 30 31 32
+}
+
+/// The hash does not depend on the order of the entries, consistently with `Eq`
+///
+/// Each entry is hashed on its own and the results are combined with XOR.
+///
+/// ```rust
+/// use diesel_pg_hstore::Hstore;
+/// use std::collections::HashSet;
+///
+/// let mut seen = HashSet::new();
+/// seen.insert(Hstore::from_array([("a", "1"), ("b", "2")]));
+/// assert!(seen.contains(&Hstore::from_array([("b", "2"), ("a", "1")])));
+/// ```
+impl Hash for Hstore {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        let combined = self.0.iter().fold(0, |acc, entry| {
+            let mut hasher = DefaultHasher::new();
+            entry.hash(&mut hasher);
+            acc ^ hasher.finish()
+        });
+        state.write_usize(self.0.len());
+        state.write_u64(combined);
+    }
+}
+
+/// The keys and values are copied into owned strings
+///
+/// ```rust
+/// use diesel_pg_hstore::Hstore;
+///
+/// let settings: Hstore = vec![("Hello", "World")].into_iter().collect();
+/// assert_eq!(settings["Hello"], "World");
+/// ```
+impl<'a> FromIterator<(&'a str, &'a str)> for Hstore {
+    fn from_iter<T>(iter: T) -> Hstore
+        where T: IntoIterator<Item = (&'a str, &'a str)>
+    {
+        iter.into_iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+}
+
+impl<'a> Index<&'a str> for Hstore {
+    type Output = String;
+
+    #[inline]
+    fn index(&self, index: &'a str) -> &Self::Output {
+        self.0.get(index).expect("no entry found for key")
+    }
+}
+
+impl Extend<(String, String)> for Hstore {
+    fn extend<T>(&mut self, iter: T)
+        where T: IntoIterator<Item = (String, String)>
+    {
+        self.0.extend(iter)
+    }
+}
+
+/// The keys and values are copied into owned strings
+///
+/// ```rust
+/// use diesel_pg_hstore::Hstore;
+///
+/// let mut settings = Hstore::new();
+/// settings.extend(vec![("Hello", "World")]);
+/// assert_eq!(settings["Hello"], "World");
+/// ```
+impl<'a> Extend<(&'a str, &'a str)> for Hstore {
+    fn extend<T>(&mut self, iter: T)
+        where T: IntoIterator<Item = (&'a str, &'a str)>
+    {
+        self.0.extend(iter.into_iter().map(|(k, v)| (k.to_string(), v.to_string())))
+    }
+}
+
+/// A view into a single entry of an Hstore, as returned by `Hstore::entry_str`
+pub enum HstoreEntry<'a, 'k> {
+    /// The key is present
+    Occupied(HstoreOccupiedEntry<'a, 'k>),
+    /// The key is absent
+    Vacant(HstoreVacantEntry<'a, 'k>),
+}
+
+/// An entry for a key present in the Hstore
+pub struct HstoreOccupiedEntry<'a, 'k> {
+    /// Only used by `remove`, once `value` is no longer needed
+    map: *mut HashMap<String, String>,
+    value: &'a mut String,
+    key: &'k str,
+}
+
+/// An entry for a key absent from the Hstore
+pub struct HstoreVacantEntry<'a, 'k> {
+    map: &'a mut HashMap<String, String>,
+    key: &'k str,
+}
+
+impl<'a, 'k> HstoreEntry<'a, 'k> {
+    /// The key of this entry
+    pub fn key(&self) -> &str {
+        match *self {
+            HstoreEntry::Occupied(ref e) => e.key(),
+            HstoreEntry::Vacant(ref e) => e.key(),
+        }
+    }
+
+    /// Insert `default` if the entry is vacant and return the value
+    pub fn or_insert(self, default: String) -> &'a mut String {
+        match self {
+            HstoreEntry::Occupied(e) => e.into_mut(),
+            HstoreEntry::Vacant(e) => e.insert(default),
+        }
+    }
+
+    /// Insert the result of `default` if the entry is vacant and return the value
+    pub fn or_insert_with<F: FnOnce() -> String>(self, default: F) -> &'a mut String {
+        match self {
+            HstoreEntry::Occupied(e) => e.into_mut(),
+            HstoreEntry::Vacant(e) => e.insert(default()),
+        }
+    }
+}
+
+impl<'a, 'k> HstoreOccupiedEntry<'a, 'k> {
+    /// The key of this entry
+    pub fn key(&self) -> &str {
+        self.key
+    }
+
+    /// The value of this entry
+    pub fn get(&self) -> &String {
+        self.value
+    }
+
+    /// The value of this entry, mutably
+    pub fn get_mut(&mut self) -> &mut String {
+        self.value
+    }
+
+    /// Convert the entry into a mutable reference to its value
+    pub fn into_mut(self) -> &'a mut String {
+        self.value
+    }
+
+    /// Replace the value of this entry, returning the old value
+    pub fn insert(&mut self, value: String) -> String {
+        ::std::mem::replace(self.get_mut(), value)
+    }
+
+    /// Remove this entry from the Hstore, returning its value
+    pub fn remove(self) -> String {
+        let HstoreOccupiedEntry { map, key, .. } = self;
+        unsafe { (*map).remove(key) }.expect("occupied entry")
+    }
+}
+
+impl<'a, 'k> HstoreVacantEntry<'a, 'k> {
+    /// The key of this entry
+    pub fn key(&self) -> &str {
+        self.key
+    }
+
+    /// Insert a value, allocating the owned key
+    pub fn insert(self, value: String) -> &'a mut String {
+        self.map.entry(self.key.to_owned()).or_insert(value)
+    }
+}
+
+/// Error returned by `Hstore::from_flat_strings` for an odd number of strings
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HstoreLenMismatch {
+    /// The number of strings given
+    pub len: usize,
+}
+
+impl fmt::Display for HstoreLenMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "expected an even number of keys and values, got {}", self.len)
+    }
+}
+
+impl StdError for HstoreLenMismatch {
+    fn description(&self) -> &str {
+        "odd number of keys and values"
+    }
+}
+
+/// Error returned for a key, value or entry count too large for the hstore wire format
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HstoreLengthOverflow {
+    /// The length which does not fit in a 4 byte signed integer
+    pub len: usize,
+}
+
+impl fmt::Display for HstoreLengthOverflow {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "length {} exceeds the hstore limit of {}", self.len, i32::max_value())
+    }
+}
+
+impl StdError for HstoreLengthOverflow {
+    fn description(&self) -> &str {
+        "length too large for hstore"
+    }
+}
+
+/// Convert a length to the 4 byte signed integer of the wire format
+fn wire_length(len: usize) -> Result<i32, HstoreLengthOverflow> {
+    if len > i32::max_value() as usize {
+        return Err(HstoreLengthOverflow { len: len });
+    }
+    Ok(len as i32)
+}
+
+/// A key overwritten by `Hstore::merge_logging`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyOverride {
+    /// The overwritten key
+    pub key: String,
+    /// The value before the merge
+    pub old: String,
+    /// The value after the merge
+    pub new: String,
+}
+
+/// A single entry, as used by `Hstore::to_kv_list` and `Hstore::from_kv_list`
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct KeyValue {
+    /// The key of the entry
+    pub key: String,
+    /// The value of the entry
+    pub value: String,
+}
+
+/// A set of changes to apply to an Hstore
+///
+/// Applying a patch first removes the keys in `remove`, then sets the entries in `set`. In SQL
+/// this is `(hstore - remove) || set`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HstorePatch {
+    /// Entries to add or overwrite
+    pub set: Hstore,
+    /// Keys to remove, sorted
+    pub remove: Vec<String>,
+}
+
+impl HstorePatch {
+    fn between(from: &Hstore, to: &Hstore) -> HstorePatch {
+        let set = to.iter()
+            .filter(|&(k, v)| from.get(k) != Some(v))
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect();
+        let mut remove: Vec<String> = from.keys()
+            .filter(|k| !to.contains_key(k))
+            .cloned()
+            .collect();
+        remove.sort();
+
+        HstorePatch {
+            set: set,
+            remove: remove,
+        }
+    }
+
+    /// Check if applying the patch changes nothing
+    pub fn is_empty(&self) -> bool {
+        self.set.is_empty() && self.remove.is_empty()
+    }
+
+    /// Apply the patch to an Hstore
+    pub fn apply(&self, hstore: &mut Hstore) {
+        for k in &self.remove {
+            hstore.remove(k);
+        }
+        hstore.extend(self.set.iter().map(|(k, v)| (k.clone(), v.clone())));
+    }
+}
+
+/// Check if a key or value must be double quoted in the hstore text format
+///
+/// Empty strings, strings containing whitespace, `=`, `>`, `,`, `"` or `\` and the bare word
+/// `NULL` can not be written without quotes.
+///
+/// ```rust
+/// use diesel_pg_hstore::needs_quoting;
+///
+/// assert!(!needs_quoting("a"));
+/// assert!(needs_quoting("b c"));
+/// assert!(needs_quoting("NULL"));
+/// ```
+pub fn needs_quoting(s: &str) -> bool {
+    s.is_empty() ||
+        s.eq_ignore_ascii_case("null") ||
+        s.chars().any(|c| c.is_whitespace() || "=>,\"\\".contains(c))
+}
+
+/// An Hstore wrapper type which keeps track of null values.
+///
+/// Like `Hstore`, this is both the Rust type and the SQL type, so a column can be declared as
+/// `HstoreNullable` in `table!` to read and write entries having a null value.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HstoreNullable(HashMap<String, Option<String>>);
+
+/// You can deref the HstoreNullable into it's backing HashMap
+impl Deref for HstoreNullable {
+    type Target = HashMap<String, Option<String>>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
 
-            if count < 0 {
-                return Err("Invalid entry count for hstore".into());
-            }
+/// You can mutably deref the HstoreNullable into it's backing HashMap
+impl DerefMut for HstoreNullable {
+    fn deref_mut(&mut self) -> &mut HashMap<String, Option<String>> {
+        &mut self.0
+    }
+}
 
-            let mut entries = HstoreIterator {
-                remaining: count,
-                buf: buf,
-            };
+impl HstoreNullable {
+    /// Create a new HstoreNullable object
+    pub fn new() -> HstoreNullable {
+        HstoreNullable(HashMap::new())
+    }
 
-            let mut map = HashMap::new();
+    /// Create a new HstoreNullable from an existing hashmap
+    pub fn from_hashmap(hm: HashMap<String, Option<String>>) -> HstoreNullable {
+        HstoreNullable(hm)
+    }
 
-            while let Some((k, v)) = entries.next()? {
-                map.insert(k.into(), v.into());
-            }
+    /// Convert into an Hstore, dropping every entry having a null value
+    ///
+    /// This is lossy, and matches how null values are handled when reading an `Hstore` column.
+    ///
+    /// ```rust
+    /// use diesel_pg_hstore::HstoreNullable;
+    ///
+    /// let mut settings = HstoreNullable::new();
+    /// settings.insert("Hello".into(), Some("World".into()));
+    /// settings.insert("Nothing".into(), None);
+    ///
+    /// let settings = settings.into_lossy();
+    /// assert_eq!(settings.len(), 1);
+    /// assert_eq!(settings["Hello"], "World");
+    /// ```
+    pub fn into_lossy(self) -> Hstore {
+        self.0.into_iter()
+            .filter_map(|(k, v)| v.map(|v| (k, v)))
+            .collect()
+    }
+}
+
+/// Every value of the Hstore becomes non-null
+impl From<Hstore> for HstoreNullable {
+    fn from(hstore: Hstore) -> HstoreNullable {
+        hstore.into_iter()
+            .map(|(k, v)| (k, Some(v)))
+            .collect()
+    }
+}
+
+impl FromIterator<(String, Option<String>)> for HstoreNullable {
+    fn from_iter<T>(iter: T) -> HstoreNullable
+        where T: IntoIterator<Item = (String, Option<String>)>
+    {
+        HstoreNullable(HashMap::from_iter(iter))
+    }
+}
+
+/// Query the version of the hstore extension installed in the database
+///
+/// Some hstore functions are only available in newer versions of the extension, which this allows
+/// checking for at runtime. A `NotFound` error is returned when the extension is not installed.
+///
+/// | Function | Extension version |
+/// |----------|-------------------|
+/// | `hstore_to_json`, `hstore_to_json_loose` | 1.1 |
+/// | `hstore_to_jsonb`, `hstore_to_jsonb_loose` | 1.3 |
+///
+/// The operators provided by this crate work with every version of the extension.
+pub fn hstore_extension_version(conn: &PgConnection) -> QueryResult<String> {
+    use diesel::prelude::*;
+    use self::schema::pg_extension::dsl::*;
+
+    pg_extension
+        .select(extversion)
+        .filter(extname.eq("hstore"))
+        .get_result(conn)
+}
+
+mod schema {
+    table! {
+        pg_extension (extname) {
+            extname -> Text,
+            extversion -> Text,
+        }
+    }
+}
+
+mod impls {
+    use std::fmt;
+    use std::str;
+    use std::error::Error as StdError;
+    use std::io::Write;
+    use std::collections::{HashMap, HashSet};
+    use fallible_iterator::FallibleIterator;
+    use byteorder::{ReadBytesExt, WriteBytesExt, BigEndian};
+    use diesel::types::impls::option::UnexpectedNullError;
+    use diesel::Queryable;
+    use diesel::expression::AsExpression;
+    use diesel::expression::bound::Bound;
+    use diesel::pg::Pg;
+    use diesel::query_builder::QueryId;
+    use diesel::row::Row;
+    use diesel::types::*;
+
+    #[cfg(feature = "indexmap")]
+    use indexmap::IndexMap;
+
+    use super::{wire_length, Hstore, HstoreNullable};
+    #[cfg(feature = "indexmap")]
+    use super::HstoreOrdered;
+
+    impl HasSqlType<Hstore> for Pg {
+        fn metadata(lookup: &Self::MetadataLookup) -> Self::TypeMetadata {
+            lookup.lookup_type("hstore")
+        }
+    }
+
+    impl NotNull for Hstore {}
+    impl SingleValue for Hstore {}
+
+    impl QueryId for Hstore {
+        type QueryId = Self;
+
+        const HAS_STATIC_QUERY_ID: bool = true;
+    }
+
+    impl Queryable<Hstore, Pg> for Hstore {
+        type Row = Self;
+
+        fn build(row: Self::Row) -> Self {
+            row
+        }
+    }
+
+    impl<'a> AsExpression<Hstore> for &'a Hstore {
+        type Expression = Bound<Hstore, &'a Hstore>;
+
+        fn as_expression(self) -> Self::Expression {
+            Bound::new(self)
+        }
+    }
 
-            Ok(Hstore(map))
+    impl FromSql<Hstore, Pg> for Hstore {
+        fn from_sql(bytes: Option<&[u8]>) -> Result<Self, Box<StdError + Send + Sync>> {
+            match bytes {
+                Some(bytes) => Hstore::from_wire_bytes(bytes),
+                None => Err(Box::new(UnexpectedNullError {
+                    msg: "Unexpected null for non-null column".to_string(),
+                })),
+            }
         }
     }
 
@@ -398,76 +1650,508 @@ mod impls {
         fn to_sql<W>(&self, out: &mut ToSqlOutput<W, Pg>) -> Result<IsNull, Box<StdError + Send + Sync>>
             where W: Write
         {
-            let mut buf: Vec<u8> = Vec::new();
-            buf.extend_from_slice(&[0; 4]);
+            self.write_wire_bytes(out)?;
+            Ok(IsNull::No)
+        }
+    }
+
+    impl HasSqlType<HstoreNullable> for Pg {
+        fn metadata(lookup: &Self::MetadataLookup) -> Self::TypeMetadata {
+            lookup.lookup_type("hstore")
+        }
+    }
+
+    impl NotNull for HstoreNullable {}
+    impl SingleValue for HstoreNullable {}
+
+    impl QueryId for HstoreNullable {
+        type QueryId = Self;
+
+        const HAS_STATIC_QUERY_ID: bool = true;
+    }
+
+    impl Queryable<HstoreNullable, Pg> for HstoreNullable {
+        type Row = Self;
+
+        fn build(row: Self::Row) -> Self {
+            row
+        }
+    }
+
+    impl<'a> AsExpression<HstoreNullable> for &'a HstoreNullable {
+        type Expression = Bound<HstoreNullable, &'a HstoreNullable>;
+
+        fn as_expression(self) -> Self::Expression {
+            Bound::new(self)
+        }
+    }
+
+    impl FromSql<HstoreNullable, Pg> for HstoreNullable {
+        fn from_sql(bytes: Option<&[u8]>) -> Result<Self, Box<StdError + Send + Sync>> {
+            match bytes {
+                Some(bytes) => Ok(decode_nullable(bytes)?),
+                None => Err(Box::new(UnexpectedNullError {
+                    msg: "Unexpected null for non-null column".to_string(),
+                })),
+            }
+        }
+    }
+
+    impl FromSqlRow<HstoreNullable, Pg> for HstoreNullable {
+        fn build_from_row<T: Row<Pg>>(row: &mut T) -> Result<Self, Box<StdError + Send + Sync>> {
+            HstoreNullable::from_sql(row.take())
+        }
+    }
+
+    impl ToSql<HstoreNullable, Pg> for HstoreNullable {
+        fn to_sql<W>(&self, out: &mut ToSqlOutput<W, Pg>) -> Result<IsNull, Box<StdError + Send + Sync>>
+            where W: Write
+        {
+            let entries = self.0.iter().map(|(k, v)| (k.as_str(), v.as_ref().map(String::as_str)));
+            write_entries(self.0.len(), entries, out)?;
+            Ok(IsNull::No)
+        }
+    }
+
+    #[cfg(feature = "indexmap")]
+    impl HasSqlType<HstoreOrdered> for Pg {
+        fn metadata(lookup: &Self::MetadataLookup) -> Self::TypeMetadata {
+            lookup.lookup_type("hstore")
+        }
+    }
+
+    #[cfg(feature = "indexmap")]
+    impl NotNull for HstoreOrdered {}
+    #[cfg(feature = "indexmap")]
+    impl SingleValue for HstoreOrdered {}
+
+    #[cfg(feature = "indexmap")]
+    impl QueryId for HstoreOrdered {
+        type QueryId = Self;
+
+        const HAS_STATIC_QUERY_ID: bool = true;
+    }
+
+    #[cfg(feature = "indexmap")]
+    impl Queryable<HstoreOrdered, Pg> for HstoreOrdered {
+        type Row = Self;
+
+        fn build(row: Self::Row) -> Self {
+            row
+        }
+    }
 
-            let mut count = 0;
-            for (key, value) in &self.0 {
-                count += 1;
+    #[cfg(feature = "indexmap")]
+    impl<'a> AsExpression<HstoreOrdered> for &'a HstoreOrdered {
+        type Expression = Bound<HstoreOrdered, &'a HstoreOrdered>;
+
+        fn as_expression(self) -> Self::Expression {
+            Bound::new(self)
+        }
+    }
 
-                write_pascal_string(&key, &mut buf)?;
-                write_pascal_string(&value, &mut buf)?;
+    #[cfg(feature = "indexmap")]
+    impl FromSql<HstoreOrdered, Pg> for HstoreOrdered {
+        fn from_sql(bytes: Option<&[u8]>) -> Result<Self, Box<StdError + Send + Sync>> {
+            match bytes {
+                Some(bytes) => Ok(decode_ordered(bytes)?),
+                None => Err(Box::new(UnexpectedNullError {
+                    msg: "Unexpected null for non-null column".to_string(),
+                })),
             }
+        }
+    }
 
-            let count = count as i32;
-            (&mut buf[0..4])
-                .write_i32::<BigEndian>(count)
-                .unwrap();
+    #[cfg(feature = "indexmap")]
+    impl FromSqlRow<HstoreOrdered, Pg> for HstoreOrdered {
+        fn build_from_row<T: Row<Pg>>(row: &mut T) -> Result<Self, Box<StdError + Send + Sync>> {
+            HstoreOrdered::from_sql(row.take())
+        }
+    }
 
-            out.write_all(&buf)?;
+    #[cfg(feature = "indexmap")]
+    impl ToSql<HstoreOrdered, Pg> for HstoreOrdered {
+        fn to_sql<W>(&self, out: &mut ToSqlOutput<W, Pg>) -> Result<IsNull, Box<StdError + Send + Sync>>
+            where W: Write
+        {
+            let entries = self.iter().map(|(k, v)| (k.as_str(), Some(v.as_str())));
+            write_entries(self.len(), entries, out)?;
             Ok(IsNull::No)
         }
     }
 
-    fn write_pascal_string(s: &str, buf: &mut Vec<u8>) -> Result<(), Box<StdError + Sync + Send>> {
-        let size: i32 = s.len() as i32;
-        buf.write_i32::<BigEndian>(size).unwrap();
-        buf.extend_from_slice(s.as_bytes());
+    impl Hstore {
+        /// Encode the Hstore in the Postgres binary wire format
+        pub fn to_wire_bytes(&self) -> Result<Vec<u8>, Box<StdError + Send + Sync>> {
+            let mut buf = Vec::with_capacity(self.wire_size());
+            self.write_wire_bytes(&mut buf)?;
+            Ok(buf)
+        }
+
+        /// Write the Hstore in the Postgres binary wire format to `out`
+        ///
+        /// The entry count is known up front, so nothing is buffered: this is how `ToSql` avoids
+        /// holding a second copy of large values in memory.
+        pub fn write_wire_bytes<W: Write>(&self, out: &mut W) -> Result<(), Box<StdError + Send + Sync>> {
+            write_entries(self.0.len(), self.0.iter().map(|(k, v)| (k.as_str(), Some(v.as_str()))), out)
+        }
+
+        /// Encode the Hstore in the Postgres binary wire format, with the entries sorted by key
+        ///
+        /// `to_wire_bytes` writes the entries in the arbitrary order of the map, so equal Hstores
+        /// may encode differently. This is the form to hash or sign, as equal Hstores always give
+        /// the same bytes. Postgres decodes it like any other hstore.
+        ///
+        /// ```rust
+        /// use diesel_pg_hstore::Hstore;
+        ///
+        /// let a = Hstore::from_array([("x", "1"), ("y", "2")]);
+        /// let b = Hstore::from_array([("y", "2"), ("x", "1")]);
+        /// assert_eq!(a.canonical_bytes(), b.canonical_bytes());
+        /// ```
+        pub fn canonical_bytes(&self) -> Vec<u8> {
+            let entries = self.entries_by_key()
+                .into_iter()
+                .map(|(k, v)| (k.as_str(), Some(v.as_str())));
+
+            let mut buf = Vec::with_capacity(self.wire_size());
+            write_entries(self.0.len(), entries, &mut buf).expect("writing to a Vec to succeed");
+            buf
+        }
+
+        /// Encode only the entries for `keys` in the Postgres binary wire format
+        ///
+        /// Keys which are absent from the Hstore are skipped, as are repeated keys. This avoids
+        /// building an intermediate Hstore when sending a subset of the entries.
+        pub fn encode_subset(&self, keys: &[&str]) -> Result<Vec<u8>, Box<StdError + Send + Sync>> {
+            let mut seen = HashSet::new();
+            let entries: Vec<(&str, Option<&str>)> = keys.iter()
+                .filter(|k| seen.insert(**k))
+                .filter_map(|k| self.0.get(*k).map(|v| (*k, Some(v.as_str()))))
+                .collect();
+
+            let mut buf = Vec::new();
+            write_entries(entries.len(), entries.into_iter(), &mut buf)?;
+            Ok(buf)
+        }
+
+        /// Decode an Hstore from the Postgres binary wire format
+        ///
+        /// Entries having a null value are ignored. A malformed buffer gives a boxed
+        /// `HstoreWireError`, which is also what `FromSql` returns, so callers can downcast to
+        /// tell the failures apart.
+        pub fn from_wire_bytes(buf: &[u8]) -> Result<Hstore, Box<StdError + Send + Sync>> {
+            Ok(decode(buf)?)
+        }
+
+        /// Append the Hstore to a `COPY ... WITH (FORMAT binary)` stream, as a single field
+        ///
+        /// The field is the 4 byte length followed by the Postgres binary wire format. An Hstore
+        /// whose encoding is longer than `i32::MAX` bytes gives an error, and `buf` is then left
+        /// as it was, without a partial field.
+        pub fn to_copy_binary_field(&self, buf: &mut Vec<u8>) -> Result<(), Box<StdError + Send + Sync>> {
+            let start = buf.len();
+            let size = self.wire_size();
+            let result = wire_length(size)
+                .map_err(|e| e.into())
+                .and_then(|len| {
+                    buf.reserve(4 + size);
+                    buf.write_i32::<BigEndian>(len)?;
+                    self.write_wire_bytes(buf)
+                });
+
+            if result.is_err() {
+                buf.truncate(start);
+            }
+            result
+        }
+
+        /// Decode a single field of a `COPY ... WITH (FORMAT binary)` stream
+        ///
+        /// A field with a length of -1 is a null, for which `None` is returned. `buf` must hold
+        /// exactly one field.
+        ///
+        /// ```rust
+        /// use diesel_pg_hstore::Hstore;
+        ///
+        /// let mut settings = Hstore::new();
+        /// settings.insert("Hello".into(), "World".into());
+        ///
+        /// let mut buf = Vec::new();
+        /// settings.to_copy_binary_field(&mut buf).unwrap();
+        /// assert_eq!(Hstore::from_copy_binary_field(&buf).unwrap(), Some(settings));
+        /// ```
+        pub fn from_copy_binary_field(mut buf: &[u8]) -> Result<Option<Hstore>, HstoreWireError> {
+            let len = read_length(&mut buf)?;
+            if len < 0 {
+                return match buf.len() {
+                    0 => Ok(None),
+                    n => Err(HstoreWireError::TrailingBytes(n)),
+                };
+            }
+
+            let len = len as usize;
+            if buf.len() < len {
+                return Err(HstoreWireError::Truncated);
+            }
+            if buf.len() > len {
+                return Err(HstoreWireError::TrailingBytes(buf.len() - len));
+            }
+            decode(buf).map(Some)
+        }
+
+        /// Find the value for a single key in the Postgres binary wire format
+        ///
+        /// The entries are scanned in place without building a map. The whole buffer is scanned
+        /// and validated, so the result always agrees with `from_wire_bytes`: when a key is
+        /// repeated, the last value wins, and a null value is ignored just like an absent key.
+        ///
+        /// ```rust
+        /// use diesel_pg_hstore::Hstore;
+        ///
+        /// let mut settings = Hstore::new();
+        /// settings.insert("Hello".into(), "World".into());
+        /// let bytes = settings.to_wire_bytes().unwrap();
+        ///
+        /// assert_eq!(Hstore::extract_key_from_wire(&bytes, "Hello").unwrap(), Some("World".into()));
+        /// assert_eq!(Hstore::extract_key_from_wire(&bytes, "Bye").unwrap(), None);
+        /// ```
+        pub fn extract_key_from_wire(buf: &[u8], key: &str) -> Result<Option<String>, HstoreWireError> {
+            let mut entries = HstoreIterator::new(buf)?;
+            let mut found = None;
+
+            while let Some((k, v)) = entries.next()? {
+                if k == key {
+                    found = Some(v);
+                }
+            }
+
+            Ok(found.map(String::from))
+        }
+
+        /// Measure the entries in the Postgres binary wire format without decoding them into a map
+        ///
+        /// The whole buffer is validated, so this fails whenever `from_wire_bytes` would.
+        pub fn decode_stats(buf: &[u8]) -> Result<HstoreDecodeStats, HstoreWireError> {
+            let mut entries = HstoreIterator::new(buf)?;
+            let mut stats = HstoreDecodeStats::default();
+
+            while let Some((k, v)) = entries.consume()? {
+                stats.entries += 1;
+                stats.key_bytes += k.len();
+                match v {
+                    Some(v) => stats.value_bytes += v.len(),
+                    None => stats.null_values += 1,
+                }
+            }
+
+            Ok(stats)
+        }
+    }
+
+    /// Statistics about hstore wire bytes, as returned by `Hstore::decode_stats`
+    #[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+    pub struct HstoreDecodeStats {
+        /// The number of entries, including those having a null value
+        pub entries: usize,
+        /// The number of entries having a null value
+        pub null_values: usize,
+        /// The total byte length of the keys
+        pub key_bytes: usize,
+        /// The total byte length of the non-null values
+        pub value_bytes: usize,
+    }
+
+    /// Error returned when decoding malformed hstore wire bytes
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum HstoreWireError {
+        /// The buffer ended in the middle of a length prefix or the entry count
+        Truncated,
+        /// The entry count is negative
+        NegativeCount(i32),
+        /// A key length is negative or longer than the rest of the buffer
+        InvalidKeyLength(i32),
+        /// A value length is longer than the rest of the buffer
+        InvalidValueLength(i32),
+        /// This many bytes are left over after the last entry
+        TrailingBytes(usize),
+        /// A key or value is not valid UTF-8
+        Utf8 {
+            /// The index of the entry holding the key or value, starting at 0
+            entry: usize,
+            /// The position of the first invalid byte in the buffer
+            offset: usize,
+            /// The error for the key or value on its own
+            error: str::Utf8Error,
+        },
+    }
+
+    impl fmt::Display for HstoreWireError {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            match *self {
+                HstoreWireError::Truncated => f.write_str(self.description()),
+                HstoreWireError::NegativeCount(n) |
+                HstoreWireError::InvalidKeyLength(n) |
+                HstoreWireError::InvalidValueLength(n) => write!(f, "{} {}", self.description(), n),
+                HstoreWireError::TrailingBytes(n) => write!(f, "{} trailing bytes", n),
+                HstoreWireError::Utf8 { entry, offset, ref error } => {
+                    write!(f, "{} at byte {} in entry {}: {}",
+                           self.description(), offset, entry, error)
+                }
+            }
+        }
+    }
+
+    impl StdError for HstoreWireError {
+        fn description(&self) -> &str {
+            match *self {
+                HstoreWireError::Truncated => "truncated hstore buffer",
+                HstoreWireError::NegativeCount(_) => "invalid entry count for hstore",
+                HstoreWireError::InvalidKeyLength(_) => "invalid key length",
+                HstoreWireError::InvalidValueLength(_) => "invalid value length",
+                HstoreWireError::TrailingBytes(_) => "invalid buffer size",
+                HstoreWireError::Utf8 { .. } => "invalid UTF-8 in hstore",
+            }
+        }
+    }
+
+    fn decode(buf: &[u8]) -> Result<Hstore, HstoreWireError> {
+        let mut entries = HstoreIterator::new(buf)?;
+
+        let mut map = HashMap::new();
+
+        while let Some((k, v)) = entries.next()? {
+            map.insert(k.into(), v.into());
+        }
+
+        Ok(Hstore(map))
+    }
+
+    fn decode_nullable(buf: &[u8]) -> Result<HstoreNullable, HstoreWireError> {
+        let mut entries = HstoreIterator::new(buf)?;
+
+        let mut map = HashMap::new();
+
+        while let Some((k, v)) = entries.consume()? {
+            map.insert(k.into(), v.map(String::from));
+        }
+
+        Ok(HstoreNullable(map))
+    }
+
+    /// Decode keeping the entries in the order they are on the wire
+    #[cfg(feature = "indexmap")]
+    fn decode_ordered(buf: &[u8]) -> Result<HstoreOrdered, HstoreWireError> {
+        let mut entries = HstoreIterator::new(buf)?;
+
+        let mut map = IndexMap::new();
+
+        while let Some((k, v)) = entries.next()? {
+            map.insert(k.into(), v.into());
+        }
+
+        Ok(HstoreOrdered::from_indexmap(map))
+    }
+
+    fn write_entries<'a, I, W>(count: usize, entries: I, out: &mut W)
+        -> Result<(), Box<StdError + Send + Sync>>
+        where I: Iterator<Item = (&'a str, Option<&'a str>)>,
+              W: Write
+    {
+        out.write_i32::<BigEndian>(wire_length(count)?)?;
+
+        for (key, value) in entries {
+            write_pascal_string(key, out)?;
+            match value {
+                Some(value) => write_pascal_string(value, out)?,
+                None => out.write_i32::<BigEndian>(-1)?,
+            }
+        }
+
+        Ok(())
+    }
+
+    fn write_pascal_string<W: Write>(s: &str, out: &mut W) -> Result<(), Box<StdError + Sync + Send>> {
+        out.write_i32::<BigEndian>(wire_length(s.len())?)?;
+        out.write_all(s.as_bytes())?;
         Ok(())
     }
 
     struct HstoreIterator<'a> {
+        count: i32,
         remaining: i32,
         buf: &'a [u8],
+        len: usize,
     }
 
     impl<'a> HstoreIterator<'a> {
-        fn consume(&mut self) -> Result<Option<(&'a str, Option<&'a str>)>, Box<StdError + Sync + Send>> {
+        /// Read the entry count and start iterating over the entries after it
+        fn new(mut buf: &'a [u8]) -> Result<Self, HstoreWireError> {
+            let len = buf.len();
+            let count = read_length(&mut buf)?;
+
+            if count < 0 {
+                return Err(HstoreWireError::NegativeCount(count));
+            }
+
+            Ok(HstoreIterator {
+                count: count,
+                remaining: count,
+                buf: buf,
+                len: len,
+            })
+        }
+
+        /// Split off the next `len` bytes as a string, locating any invalid UTF-8 in the buffer
+        fn take_str(&mut self, len: usize) -> Result<&'a str, HstoreWireError> {
+            let (s, buf) = self.buf.split_at(len);
+            let s = str::from_utf8(s).map_err(|error| HstoreWireError::Utf8 {
+                entry: (self.count - self.remaining - 1) as usize,
+                offset: self.len - self.buf.len() + error.valid_up_to(),
+                error: error,
+            })?;
+            self.buf = buf;
+            Ok(s)
+        }
+
+        fn consume(&mut self) -> Result<Option<(&'a str, Option<&'a str>)>, HstoreWireError> {
             if self.remaining == 0 {
                 if !self.buf.is_empty() {
-                    return Err("invalid buffer size".into());
+                    return Err(HstoreWireError::TrailingBytes(self.buf.len()));
                 }
                 return Ok(None);
             }
 
             self.remaining -= 1;
 
-            let key_len = self.buf.read_i32::<BigEndian>()?;
-            if key_len < 0 {
-                return Err("invalid key length".into());
+            let key_len = read_length(&mut self.buf)?;
+            if key_len < 0 || key_len as usize > self.buf.len() {
+                return Err(HstoreWireError::InvalidKeyLength(key_len));
             }
-            let (key, buf) = self.buf.split_at(key_len as usize);
-            let key = str::from_utf8(key)?;
-            self.buf = buf;
+            let key = self.take_str(key_len as usize)?;
 
-            let value_len = self.buf.read_i32::<BigEndian>()?;
+            let value_len = read_length(&mut self.buf)?;
             let value = if value_len < 0 {
                 None
             }
+            else if value_len as usize > self.buf.len() {
+                return Err(HstoreWireError::InvalidValueLength(value_len));
+            }
             else {
-                let (value, buf) = self.buf.split_at(value_len as usize);
-                let value = str::from_utf8(value)?;
-                self.buf = buf;
-                Some(value)
+                Some(self.take_str(value_len as usize)?)
             };
 
             Ok(Some((key, value)))
         }
     }
 
+    fn read_length(buf: &mut &[u8]) -> Result<i32, HstoreWireError> {
+        buf.read_i32::<BigEndian>().map_err(|_| HstoreWireError::Truncated)
+    }
+
     impl<'a> FallibleIterator for HstoreIterator<'a> {
         type Item = (&'a str, &'a str);
-        type Error = Box<StdError + Sync + Send>;
+        type Error = HstoreWireError;
 
         #[inline]
         fn next(&mut self) -> Result<Option<Self::Item>, Self::Error> {
@@ -488,3 +2172,22 @@ mod impls {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{wire_length, HstoreLengthOverflow};
+
+    #[test]
+    fn wire_length_limit() {
+        assert_eq!(wire_length(0), Ok(0));
+        assert_eq!(wire_length(i32::max_value() as usize), Ok(i32::max_value()));
+    }
+
+    #[test]
+    fn wire_length_overflow() {
+        let len = i32::max_value() as usize + 1;
+        assert_eq!(wire_length(len), Err(HstoreLengthOverflow { len: len }));
+        assert_eq!(wire_length(usize::max_value()),
+                   Err(HstoreLengthOverflow { len: usize::max_value() }));
+    }
+}
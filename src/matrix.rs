@@ -0,0 +1,129 @@
+//! The two dimensional text array returned by the `%#` operator
+
+use std::error::Error as StdError;
+use std::str;
+
+use byteorder::{BigEndian, ReadBytesExt};
+use diesel::Queryable;
+use diesel::pg::Pg;
+use diesel::query_builder::QueryId;
+use diesel::row::Row;
+use diesel::types::impls::option::UnexpectedNullError;
+use diesel::types::{Array, FromSql, FromSqlRow, HasSqlType, NotNull, SingleValue, Text};
+
+/// The SQL type of a two dimensional `text[]`, such as the `{{key,value},...}` array of
+/// `HstoreOpExtensions::to_matrix`
+///
+/// Diesel only decodes single dimension arrays, so this loads into a `Vec<Vec<String>>` or a
+/// `Vec<Vec<Option<String>>>` instead. With `Vec<Vec<String>>`, rows holding a null are skipped,
+/// just like an `Hstore` ignores entries having a null value.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TextMatrix;
+
+impl HasSqlType<TextMatrix> for Pg {
+    fn metadata(lookup: &Self::MetadataLookup) -> Self::TypeMetadata {
+        <Pg as HasSqlType<Array<Text>>>::metadata(lookup)
+    }
+}
+
+impl NotNull for TextMatrix {}
+impl SingleValue for TextMatrix {}
+
+impl QueryId for TextMatrix {
+    type QueryId = Self;
+
+    const HAS_STATIC_QUERY_ID: bool = true;
+}
+
+impl FromSql<TextMatrix, Pg> for Vec<Vec<Option<String>>> {
+    fn from_sql(bytes: Option<&[u8]>) -> Result<Self, Box<StdError + Send + Sync>> {
+        match bytes {
+            Some(bytes) => decode(bytes),
+            None => Err(Box::new(UnexpectedNullError {
+                msg: "Unexpected null for non-null column".to_string(),
+            })),
+        }
+    }
+}
+
+impl FromSqlRow<TextMatrix, Pg> for Vec<Vec<Option<String>>> {
+    fn build_from_row<T: Row<Pg>>(row: &mut T) -> Result<Self, Box<StdError + Send + Sync>> {
+        <Self as FromSql<TextMatrix, Pg>>::from_sql(row.take())
+    }
+}
+
+impl Queryable<TextMatrix, Pg> for Vec<Vec<Option<String>>> {
+    type Row = Self;
+
+    fn build(row: Self::Row) -> Self {
+        row
+    }
+}
+
+impl FromSql<TextMatrix, Pg> for Vec<Vec<String>> {
+    fn from_sql(bytes: Option<&[u8]>) -> Result<Self, Box<StdError + Send + Sync>> {
+        let rows = <Vec<Vec<Option<String>>> as FromSql<TextMatrix, Pg>>::from_sql(bytes)?;
+        Ok(rows.into_iter().filter_map(|row| row.into_iter().collect()).collect())
+    }
+}
+
+impl FromSqlRow<TextMatrix, Pg> for Vec<Vec<String>> {
+    fn build_from_row<T: Row<Pg>>(row: &mut T) -> Result<Self, Box<StdError + Send + Sync>> {
+        <Self as FromSql<TextMatrix, Pg>>::from_sql(row.take())
+    }
+}
+
+impl Queryable<TextMatrix, Pg> for Vec<Vec<String>> {
+    type Row = Self;
+
+    fn build(row: Self::Row) -> Self {
+        row
+    }
+}
+
+/// Decode the binary format of a two dimensional array of text
+///
+/// An empty array has no dimensions at all, and decodes to no rows.
+fn decode(mut bytes: &[u8]) -> Result<Vec<Vec<Option<String>>>, Box<StdError + Send + Sync>> {
+    let dimensions = bytes.read_i32::<BigEndian>()?;
+    let _has_null = bytes.read_i32::<BigEndian>()?;
+    let _oid = bytes.read_u32::<BigEndian>()?;
+    if dimensions == 0 {
+        return Ok(Vec::new());
+    }
+    if dimensions != 2 {
+        return Err(format!("expected a two dimensional array, found {} dimensions", dimensions).into());
+    }
+
+    let rows = bytes.read_i32::<BigEndian>()?;
+    let _lower_bound = bytes.read_i32::<BigEndian>()?;
+    let columns = bytes.read_i32::<BigEndian>()?;
+    let _lower_bound = bytes.read_i32::<BigEndian>()?;
+    if rows < 0 || columns < 0 {
+        return Err("invalid array dimensions".into());
+    }
+
+    let mut matrix = Vec::with_capacity(rows as usize);
+    for _ in 0..rows {
+        let mut row = Vec::with_capacity(columns as usize);
+        for _ in 0..columns {
+            let len = bytes.read_i32::<BigEndian>()?;
+            if len < 0 {
+                row.push(None);
+                continue;
+            }
+            if len as usize > bytes.len() {
+                return Err("invalid array element length".into());
+            }
+            let (element, rest) = bytes.split_at(len as usize);
+            row.push(Some(str::from_utf8(element)?.to_string()));
+            bytes = rest;
+        }
+        matrix.push(row);
+    }
+
+    if !bytes.is_empty() {
+        return Err(format!("{} trailing bytes", bytes.len()).into());
+    }
+    Ok(matrix)
+}
@@ -0,0 +1,141 @@
+//! An Hstore keeping its entries in insertion order, enabled by the `indexmap` feature
+
+use std::fmt;
+use std::iter::FromIterator;
+use std::ops::{Deref, DerefMut};
+
+use indexmap::IndexMap;
+use indexmap::map::{IntoIter, Iter, IterMut};
+
+use super::Hstore;
+use text::write_entry;
+
+/// An Hstore wrapper type which keeps its entries in insertion order
+///
+/// This is a separate type rather than a different backing for `Hstore`, so enabling the feature
+/// does not change `Hstore` for other crates in the same build. Like `Hstore`, this is both the
+/// Rust type and the SQL type, so a column can be declared as `HstoreOrdered` in `table!`.
+///
+/// Iteration, `Display` and `ToSql` all follow insertion order. Postgres does not keep that order
+/// though: it stores the entries sorted by key length and then by key, which is the order they
+/// come back in when read.
+///
+/// ```rust
+/// use diesel_pg_hstore::HstoreOrdered;
+///
+/// let mut settings = HstoreOrdered::new();
+/// settings.insert("size".into(), "large".into());
+/// settings.insert("color".into(), "blue".into());
+///
+/// assert_eq!(settings.to_string(), "size=>large, color=>blue");
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HstoreOrdered(IndexMap<String, String>);
+
+/// You can deref the HstoreOrdered into it's backing IndexMap
+impl Deref for HstoreOrdered {
+    type Target = IndexMap<String, String>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+/// You can mutably deref the HstoreOrdered into it's backing IndexMap
+impl DerefMut for HstoreOrdered {
+    fn deref_mut(&mut self) -> &mut IndexMap<String, String> {
+        &mut self.0
+    }
+}
+
+impl HstoreOrdered {
+    /// Create a new HstoreOrdered object
+    pub fn new() -> HstoreOrdered {
+        HstoreOrdered(IndexMap::new())
+    }
+
+    /// Create a new HstoreOrdered from an existing indexmap
+    pub fn from_indexmap(map: IndexMap<String, String>) -> HstoreOrdered {
+        HstoreOrdered(map)
+    }
+
+    /// The entries in the order they were inserted
+    ///
+    /// Overwriting the value of a key keeps its place, and inserting a removed key again puts it
+    /// at the end. Note that `swap_remove` moves the last entry into the place of the removed one.
+    ///
+    /// ```rust
+    /// use diesel_pg_hstore::HstoreOrdered;
+    ///
+    /// let mut settings = HstoreOrdered::new();
+    /// settings.insert("b".into(), "1".into());
+    /// settings.insert("a".into(), "2".into());
+    ///
+    /// let keys: Vec<&String> = settings.insertion_order_pairs().into_iter().map(|(k, _)| k).collect();
+    /// assert_eq!(keys, vec!["b", "a"]);
+    /// ```
+    pub fn insertion_order_pairs(&self) -> Vec<(&String, &String)> {
+        self.0.iter().collect()
+    }
+}
+
+impl IntoIterator for HstoreOrdered {
+    type Item = (String, String);
+    type IntoIter = IntoIter<String, String>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a HstoreOrdered {
+    type Item = (&'a String, &'a String);
+    type IntoIter = Iter<'a, String, String>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a mut HstoreOrdered {
+    type Item = (&'a String, &'a mut String);
+    type IntoIter = IterMut<'a, String, String>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+impl FromIterator<(String, String)> for HstoreOrdered {
+    fn from_iter<T>(iter: T) -> HstoreOrdered
+        where T: IntoIterator<Item = (String, String)>
+    {
+        HstoreOrdered(IndexMap::from_iter(iter))
+    }
+}
+
+/// The entries are kept in the arbitrary order the Hstore iterates in
+impl From<Hstore> for HstoreOrdered {
+    fn from(hstore: Hstore) -> HstoreOrdered {
+        hstore.into_iter().collect()
+    }
+}
+
+impl From<HstoreOrdered> for Hstore {
+    fn from(ordered: HstoreOrdered) -> Hstore {
+        ordered.into_iter().collect()
+    }
+}
+
+/// Render the hstore text representation, in insertion order
+impl fmt::Display for HstoreOrdered {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, (k, v)) in self.0.iter().enumerate() {
+            if i > 0 {
+                f.write_str(", ")?;
+            }
+            write_entry(f, k, v)?;
+        }
+        Ok(())
+    }
+}
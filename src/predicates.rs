@@ -0,0 +1,348 @@
+//! Hstore operators for use in Diesel queries
+//!
+//! The operators are exposed as methods on hstore expressions through the `HstoreOpExtensions`
+//! trait.
+//!
+//! Comparing two hstores with diesel's `eq` emits the hstore `=` operator, which compares the
+//! entries regardless of the order they were written in. It does not compare text
+//! representations.
+//!
+//! Unlike `jsonb`, hstore has no `->>` operator: its values are always text, so `->` already
+//! returns `text`. The value accessors are `get_value` (with `get_text` as an alias for readers
+//! coming from `jsonb`) and `get_values` for several keys at once. To keep the keys along with
+//! the values, use `slice_keys`, or `to_flat_array` and `to_matrix` for every
+//! entry.
+//!
+//! ```rust
+//! # #[macro_use] extern crate diesel;
+//! # extern crate diesel_pg_hstore;
+//! use diesel::prelude::*;
+//! use diesel_pg_hstore::HstoreOpExtensions;
+//!
+//! table! {
+//!     use diesel::types::*;
+//!     use diesel_pg_hstore::Hstore;
+//!
+//!     user_profile {
+//!         id -> Integer,
+//!         settings -> Hstore,
+//!     }
+//! }
+//!
+//! # fn main() {
+//! use self::user_profile::dsl::*;
+//!
+//! let active = user_profile
+//!     .select(id)
+//!     .filter(settings.value_eq("status", "active"));
+//! # }
+//! ```
+
+use diesel::dsl::sql;
+use diesel::expression::{AsExpression, BoxableExpression, Expression};
+use diesel::expression::operators::{Eq, Like};
+use diesel::pg::Pg;
+use diesel::prelude::{BoolExpressionMethods, ExpressionMethods};
+use diesel::types::{Array, Bool, Nullable, Text};
+
+use super::{Hstore, TextMatrix};
+use super::functions::{hstore_akeys, hstore_array_length, hstore_coalesce, hstore_defined,
+                       hstore_delete_key, hstore_delete_keys, hstore_delete_matching,
+                       hstore_exist, hstore_from_kv, hstore_slice};
+
+diesel_infix_operator!(HstoreGet, " -> ", Nullable<Text>, backend: Pg);
+diesel_infix_operator!(HstoreGetValues, " -> ", Array<Nullable<Text>>, backend: Pg);
+diesel_infix_operator!(HstoreContains, " @> ", backend: Pg);
+diesel_infix_operator!(HstoreContainedBy, " <@ ", backend: Pg);
+diesel_infix_operator!(HstoreConcat, " || ", Hstore, backend: Pg);
+diesel_infix_operator!(HstoreRemoveKeys, " - ", Hstore, backend: Pg);
+diesel_prefix_operator!(HstoreToFlatArray, "%% ", Array<Nullable<Text>>, backend: Pg);
+diesel_prefix_operator!(HstoreToMatrix, "%# ", TextMatrix, backend: Pg);
+
+/// The `->` operator with a `text[]` operand, under the name used by `get_array`
+pub type HstoreGetArray<L, R> = HstoreGetValues<L, R>;
+
+/// Hstore specific methods present on hstore expressions
+pub trait HstoreOpExtensions: Expression<SqlType = Hstore> + Sized {
+    /// Get the value for a key, using the `->` operator
+    ///
+    /// The value is null when the key is absent.
+    fn get_value<T>(self, key: T) -> HstoreGet<Self, T::Expression>
+        where T: AsExpression<Text>
+    {
+        HstoreGet::new(self, key.as_expression())
+    }
+
+    /// Get the value for a key as text, the same as `get_value`
+    ///
+    /// This stands in for the `->>` operator of `jsonb`, which hstore does not have.
+    fn get_text<T>(self, key: T) -> HstoreGet<Self, T::Expression>
+        where T: AsExpression<Text>
+    {
+        self.get_value(key)
+    }
+
+    /// Get the value for a key, or `default` when the key is absent or its value is null
+    ///
+    /// This is `COALESCE(hstore -> key, default)`, so the result is a non-nullable `Text`.
+    fn get_value_or<K, D>(self, key: K, default: D)
+        -> hstore_coalesce<HstoreGet<Self, K::Expression>, D>
+        where K: AsExpression<Text>,
+              D: AsExpression<Text>
+    {
+        hstore_coalesce(self.get_value(key), default)
+    }
+
+    /// Get the values for several keys, using the `->` operator with a `text[]` operand
+    ///
+    /// The values come in the order of `keys`, with nulls for absent keys.
+    fn get_values<T>(self, keys: T) -> HstoreGetValues<Self, T::Expression>
+        where T: AsExpression<Array<Text>>
+    {
+        HstoreGetValues::new(self, keys.as_expression())
+    }
+
+    /// Get the values for several keys as an array, the same as `get_values`
+    fn get_array<T>(self, keys: T) -> HstoreGetArray<Self, T::Expression>
+        where T: AsExpression<Array<Text>>
+    {
+        self.get_values(keys)
+    }
+
+    /// Set a single key, as in `hstore || hstore(key, value)`
+    ///
+    /// The other entries are left untouched, so this is meant for updates such as
+    /// `update(table).set(store.eq(store.set_key("theme", "dark")))`.
+    fn set_key<K, V>(self, key: K, value: V) -> HstoreConcat<Self, hstore_from_kv<K, V>>
+        where K: AsExpression<Text>,
+              V: AsExpression<Text>
+    {
+        HstoreConcat::new(self, hstore_from_kv(key, value))
+    }
+
+    /// Delete several keys, using the `-` operator with a `text[]` operand
+    ///
+    /// Absent keys are ignored. A runtime `Vec<String>` or `&[&str]` of keys is bound as a single
+    /// array parameter, so this is meant for updates such as
+    /// `update(table).set(store.eq(store.remove_keys(keys)))`.
+    fn remove_keys<T>(self, keys: T) -> HstoreRemoveKeys<Self, T::Expression>
+        where T: AsExpression<Array<Text>>
+    {
+        HstoreRemoveKeys::new(self, keys.as_expression())
+    }
+
+    /// Delete a key, as in `delete(hstore, key)`
+    fn delete_key<T>(self, key: T) -> hstore_delete_key<Self, T>
+        where T: AsExpression<Text>
+    {
+        hstore_delete_key(self, key)
+    }
+
+    /// Delete several keys, as in `delete(hstore, keys)`
+    ///
+    /// This is the function form of `remove_keys`.
+    fn delete_keys<T>(self, keys: T) -> hstore_delete_keys<Self, T>
+        where T: AsExpression<Array<Text>>
+    {
+        hstore_delete_keys(self, keys)
+    }
+
+    /// Delete the entries whose key and value both match those of `pairs`, as in
+    /// `delete(hstore, pairs)`
+    fn delete_matching<T>(self, pairs: T) -> hstore_delete_matching<Self, T>
+        where T: AsExpression<Hstore>
+    {
+        hstore_delete_matching(self, pairs)
+    }
+
+    /// Extract the entries for several keys into a new hstore, as in `slice(hstore, keys)`
+    ///
+    /// This is the fluent form of `functions::hstore_slice`, so
+    /// `.select(store.slice_keys(vec!["a", "b"]))` selects just those keys.
+    ///
+    /// Unlike `get_array` (also named `get_values`), which returns an array with a null for every
+    /// absent key, absent keys are simply left out of the result.
+    fn slice_keys<T>(self, keys: T) -> hstore_slice<Self, T>
+        where T: AsExpression<Array<Text>>
+    {
+        hstore_slice(self, keys)
+    }
+
+    /// Count the keys in the database, as in `array_length(akeys(hstore), 1)`
+    ///
+    /// This avoids fetching the hstore to call `len` on it. The count is null, not 0, for an empty
+    /// hstore, so it loads into an `Option<i32>`.
+    fn key_count(self) -> hstore_array_length<hstore_akeys<Self>, i32> {
+        hstore_array_length(hstore_akeys(self), 1)
+    }
+
+    /// Flatten the hstore into alternating keys and values, using the `%%` operator
+    ///
+    /// The elements are nullable since entries may have a null value, so this loads into a
+    /// `Vec<Option<String>>`. The order of the entries is the one Postgres stores them in.
+    fn to_flat_array(self) -> HstoreToFlatArray<Self> {
+        HstoreToFlatArray::new(self)
+    }
+
+    /// Convert the hstore into `{key, value}` pairs, using the `%#` operator
+    ///
+    /// Each inner array holds exactly two elements, the key then the value. This loads into a
+    /// `Vec<Vec<String>>`, skipping the entries having a null value, or into a
+    /// `Vec<Vec<Option<String>>>` to keep them. The order of the entries is the one Postgres
+    /// stores them in, and an empty hstore gives no rows.
+    fn to_matrix(self) -> HstoreToMatrix<Self> {
+        HstoreToMatrix::new(self)
+    }
+
+    /// Check if the hstore has the key, even with a null value, as in `exist(hstore, key)`
+    fn has_key<T>(self, key: T) -> hstore_exist<Self, T>
+        where T: AsExpression<Text>
+    {
+        hstore_exist(self, key)
+    }
+
+    /// Check if the hstore has a non-null value for the key, as in `defined(hstore, key)`
+    ///
+    /// Unlike `has_key`, this does not match a key having a null value.
+    fn defined<T>(self, key: T) -> hstore_defined<Self, T>
+        where T: AsExpression<Text>
+    {
+        hstore_defined(self, key)
+    }
+
+    /// Compare the value for a key with `value`, as in `hstore -> key = value`
+    ///
+    /// Rows where the key is absent never match.
+    fn value_eq<K, V>(self, key: K, value: V) -> Eq<HstoreGet<Self, K::Expression>, V::Expression>
+        where K: AsExpression<Text>,
+              V: AsExpression<Nullable<Text>>
+    {
+        self.get_value(key).eq(value)
+    }
+
+    /// Match the value for a key against a `LIKE` pattern, as in `hstore -> key LIKE pattern`
+    ///
+    /// Rows where the key is absent never match, since the value is then null.
+    fn value_like<K, P>(self, key: K, pattern: P) -> Like<HstoreGet<Self, K::Expression>, P::Expression>
+        where K: AsExpression<Text>,
+              P: AsExpression<Nullable<Text>>
+    {
+        Like::new(self.get_value(key), pattern.as_expression())
+    }
+
+    /// Check if the hstore contains the given entry, as in `hstore @> hstore(key, value)`
+    fn contains_pair<K, V>(self, key: K, value: V) -> HstoreContains<Self, hstore_from_kv<K, V>>
+        where K: AsExpression<Text>,
+              V: AsExpression<Text>
+    {
+        HstoreContains::new(self, hstore_from_kv(key, value))
+    }
+
+    /// Check if the hstore is contained in the given entry, as in `hstore <@ hstore(key, value)`
+    ///
+    /// This only holds when the given entry is the only one in the hstore, or when the hstore is
+    /// empty.
+    fn is_contained_pair<K, V>(self, key: K, value: V)
+        -> HstoreContainedBy<Self, hstore_from_kv<K, V>>
+        where K: AsExpression<Text>,
+              V: AsExpression<Text>
+    {
+        HstoreContainedBy::new(self, hstore_from_kv(key, value))
+    }
+}
+
+impl<T: Expression<SqlType = Hstore>> HstoreOpExtensions for T {}
+
+/// A boxed boolean expression, as built by `HstoreFilter`
+pub type BoxedHstoreFilter<'a, QS> = Box<BoxableExpression<QS, Pg, SqlType = Bool> + 'a>;
+
+/// How the conditions of an `HstoreFilter` are combined
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FilterMode {
+    /// Every condition must hold (`AND`)
+    All,
+    /// At least one condition must hold (`OR`)
+    Any,
+}
+
+/// Builder combining several hstore conditions into a single boolean expression
+///
+/// ```rust
+/// # #[macro_use] extern crate diesel;
+/// # extern crate diesel_pg_hstore;
+/// use diesel::prelude::*;
+/// use diesel_pg_hstore::HstoreOpExtensions;
+/// use diesel_pg_hstore::predicates::HstoreFilter;
+///
+/// table! {
+///     use diesel::types::*;
+///     use diesel_pg_hstore::Hstore;
+///
+///     user_profile {
+///         id -> Integer,
+///         settings -> Hstore,
+///     }
+/// }
+///
+/// # fn main() {
+/// use self::user_profile::dsl::*;
+///
+/// let filter = HstoreFilter::<user_profile>::any()
+///     .condition(settings.value_eq("status", "active"))
+///     .condition(settings.value_eq("status", "pending"))
+///     .build();
+/// let query = user_profile.select(id).filter(filter);
+/// # }
+/// ```
+pub struct HstoreFilter<'a, QS> {
+    mode: FilterMode,
+    conditions: Vec<BoxedHstoreFilter<'a, QS>>,
+}
+
+impl<'a, QS: 'a> HstoreFilter<'a, QS> {
+    /// Create an empty filter combining its conditions with `mode`
+    pub fn new(mode: FilterMode) -> Self {
+        HstoreFilter {
+            mode: mode,
+            conditions: Vec::new(),
+        }
+    }
+
+    /// Create an empty filter requiring every condition to hold
+    pub fn all() -> Self {
+        HstoreFilter::new(FilterMode::All)
+    }
+
+    /// Create an empty filter requiring any condition to hold
+    pub fn any() -> Self {
+        HstoreFilter::new(FilterMode::Any)
+    }
+
+    /// Add a condition to the filter
+    pub fn condition<E>(mut self, condition: E) -> Self
+        where E: BoxableExpression<QS, Pg, SqlType = Bool> + 'a
+    {
+        self.conditions.push(Box::new(condition));
+        self
+    }
+
+    /// Combine the conditions into a single expression
+    ///
+    /// A filter without conditions evaluates to `TRUE` in `All` mode and to `FALSE` in `Any` mode.
+    pub fn build(self) -> BoxedHstoreFilter<'a, QS> {
+        let mode = self.mode;
+        let mut conditions = self.conditions.into_iter();
+        let first = match conditions.next() {
+            Some(first) => first,
+            None => return match mode {
+                FilterMode::All => Box::new(sql::<Bool>("TRUE")),
+                FilterMode::Any => Box::new(sql::<Bool>("FALSE")),
+            },
+        };
+
+        conditions.fold(first, |acc, condition| match mode {
+            FilterMode::All => Box::new(acc.and(condition)),
+            FilterMode::Any => Box::new(acc.or(condition)),
+        })
+    }
+}
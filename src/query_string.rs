@@ -0,0 +1,140 @@
+//! Conversions from `application/x-www-form-urlencoded` query strings
+//!
+//! This covers the common case of a web form posting settings as `a=1&b=2`, to be merged into
+//! an hstore column.
+//!
+//! ```rust
+//! # #[macro_use] extern crate diesel;
+//! # extern crate diesel_pg_hstore;
+//! use diesel::prelude::*;
+//! use diesel_pg_hstore::query_string::update_expr_from_query_string;
+//!
+//! table! {
+//!     use diesel::types::*;
+//!     use diesel_pg_hstore::Hstore;
+//!
+//!     user_profile {
+//!         id -> Integer,
+//!         settings -> Hstore,
+//!     }
+//! }
+//!
+//! # fn main() {
+//! use self::user_profile::dsl::*;
+//!
+//! let changes = update_expr_from_query_string(settings, "theme=dark&lang=en").unwrap();
+//! let query = diesel::update(user_profile.filter(id.eq(1))).set(changes);
+//! # }
+//! ```
+
+use std::error::Error as StdError;
+use std::fmt;
+
+use diesel::Column;
+use diesel::expression::bound::Bound;
+use diesel::expression::operators::Eq;
+use diesel::prelude::ExpressionMethods;
+
+use super::Hstore;
+use super::predicates::HstoreConcat;
+
+/// Error returned when decoding a malformed query string
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QueryStringError {
+    /// A `%` is not followed by two hexadecimal digits, at the given byte offset
+    InvalidEscape(usize),
+    /// A decoded key or value is not valid UTF-8
+    InvalidUtf8,
+}
+
+impl fmt::Display for QueryStringError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            QueryStringError::InvalidEscape(p) => {
+                write!(f, "{} at position {}", self.description(), p)
+            }
+            QueryStringError::InvalidUtf8 => f.write_str(self.description()),
+        }
+    }
+}
+
+impl StdError for QueryStringError {
+    fn description(&self) -> &str {
+        match *self {
+            QueryStringError::InvalidEscape(_) => "invalid percent escape",
+            QueryStringError::InvalidUtf8 => "invalid UTF-8 in decoded query string",
+        }
+    }
+}
+
+impl Hstore {
+    /// Decode a query string, as in `a=1&b=hello+world`
+    ///
+    /// `+` decodes to a space and `%XX` to the given byte. A pair without `=` gets an empty value,
+    /// and when a key is repeated the last value wins.
+    ///
+    /// ```rust
+    /// use diesel_pg_hstore::Hstore;
+    ///
+    /// let settings = Hstore::from_query_string("theme=dark&greeting=hello+world%21").unwrap();
+    /// assert_eq!(settings["theme"], "dark");
+    /// assert_eq!(settings["greeting"], "hello world!");
+    /// ```
+    pub fn from_query_string(s: &str) -> Result<Hstore, QueryStringError> {
+        let mut hstore = Hstore::new();
+        let mut offset = 0;
+        for pair in s.split('&') {
+            if !pair.is_empty() {
+                let (k, v) = match pair.find('=') {
+                    Some(i) => (&pair[..i], Some((&pair[i + 1..], offset + i + 1))),
+                    None => (pair, None),
+                };
+                let key = decode(k, offset)?;
+                let value = match v {
+                    Some((v, v_offset)) => decode(v, v_offset)?,
+                    None => String::new(),
+                };
+                hstore.insert(key, value);
+            }
+            offset += pair.len() + 1;
+        }
+        Ok(hstore)
+    }
+}
+
+/// Build an update assignment merging the entries of a query string into `column`
+///
+/// The result is `column = column || $entries`, so existing keys not in the query string are kept.
+pub fn update_expr_from_query_string<C>(column: C, s: &str)
+    -> Result<Eq<C, HstoreConcat<C, Bound<Hstore, Hstore>>>, QueryStringError>
+    where C: Column<SqlType = Hstore> + Copy
+{
+    let changes = Hstore::from_query_string(s)?;
+    Ok(column.eq(HstoreConcat::new(column, Bound::new(changes))))
+}
+
+/// Percent-decode a single key or value starting at byte `offset` of the query string
+fn decode(s: &str, offset: usize) -> Result<String, QueryStringError> {
+    let bytes = s.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => decoded.push(b' '),
+            b'%' => {
+                let byte = s.get(i + 1..i + 3)
+                    .and_then(|hex| if hex.chars().all(|c| c.is_digit(16)) {
+                        u8::from_str_radix(hex, 16).ok()
+                    } else {
+                        None
+                    })
+                    .ok_or(QueryStringError::InvalidEscape(offset + i))?;
+                decoded.push(byte);
+                i += 2;
+            }
+            b => decoded.push(b),
+        }
+        i += 1;
+    }
+    String::from_utf8(decoded).map_err(|_| QueryStringError::InvalidUtf8)
+}
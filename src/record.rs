@@ -0,0 +1,132 @@
+//! Conversion of an Hstore into a typed struct, in Rust rather than with `populate_record`
+//!
+//! ```rust
+//! use diesel_pg_hstore::{FromHstore, Hstore, HstoreFields, HstoreRecordError};
+//!
+//! #[derive(Debug, PartialEq)]
+//! struct Settings {
+//!     retries: i64,
+//!     verbose: bool,
+//!     name: Option<String>,
+//! }
+//!
+//! impl FromHstore for Settings {
+//!     fn from_hstore(fields: &mut HstoreFields) -> Result<Self, HstoreRecordError> {
+//!         Ok(Settings {
+//!             retries: fields.parse("retries")?,
+//!             verbose: fields.parse("verbose")?,
+//!             name: fields.parse_optional("name")?,
+//!         })
+//!     }
+//! }
+//!
+//! let settings = Hstore::from_array([("retries", "3"), ("verbose", "true")]);
+//! assert_eq!(settings.into_struct::<Settings>().unwrap(),
+//!            Settings { retries: 3, verbose: true, name: None });
+//! ```
+
+use std::collections::HashSet;
+use std::error::Error as StdError;
+use std::fmt;
+use std::str::FromStr;
+
+use super::Hstore;
+
+/// A type which can be built from the entries of an Hstore, see `Hstore::into_struct`
+pub trait FromHstore: Sized {
+    /// Build the value, reading each field from `fields`
+    fn from_hstore(fields: &mut HstoreFields) -> Result<Self, HstoreRecordError>;
+}
+
+/// The entries of an Hstore being read by `FromHstore`, keeping track of the keys used
+#[derive(Debug)]
+pub struct HstoreFields<'a> {
+    hstore: &'a Hstore,
+    used: HashSet<String>,
+}
+
+impl<'a> HstoreFields<'a> {
+    /// Parse the value for a required key
+    pub fn parse<T: FromStr>(&mut self, key: &str) -> Result<T, HstoreRecordError> {
+        match self.parse_optional(key)? {
+            Some(value) => Ok(value),
+            None => Err(HstoreRecordError::MissingKey(key.to_string())),
+        }
+    }
+
+    /// Parse the value for a key which may be absent
+    pub fn parse_optional<T>(&mut self, key: &str) -> Result<Option<T>, HstoreRecordError>
+        where T: FromStr
+    {
+        let value = match self.hstore.get(key) {
+            Some(value) => value,
+            None => return Ok(None),
+        };
+        self.used.insert(key.to_string());
+        match value.parse() {
+            Ok(value) => Ok(Some(value)),
+            Err(_) => {
+                Err(HstoreRecordError::InvalidValue { key: key.to_string(), value: value.clone() })
+            }
+        }
+    }
+}
+
+/// Error returned by `Hstore::into_struct`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HstoreRecordError {
+    /// A required key is absent
+    MissingKey(String),
+    /// The value for a key could not be parsed
+    InvalidValue {
+        /// The key holding the value
+        key: String,
+        /// The value which could not be parsed
+        value: String,
+    },
+    /// The keys which do not match any field, sorted
+    ExtraKeys(Vec<String>),
+}
+
+impl fmt::Display for HstoreRecordError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            HstoreRecordError::MissingKey(ref key) => write!(f, "missing key {:?}", key),
+            HstoreRecordError::InvalidValue { ref key, ref value } => {
+                write!(f, "invalid value {:?} for key {:?}", value, key)
+            }
+            HstoreRecordError::ExtraKeys(ref keys) => write!(f, "unexpected keys {:?}", keys),
+        }
+    }
+}
+
+impl StdError for HstoreRecordError {
+    fn description(&self) -> &str {
+        match *self {
+            HstoreRecordError::MissingKey(_) => "missing key",
+            HstoreRecordError::InvalidValue { .. } => "invalid value",
+            HstoreRecordError::ExtraKeys(_) => "unexpected keys",
+        }
+    }
+}
+
+impl Hstore {
+    /// Convert the entries into a struct, parsing each field with `FromStr`
+    ///
+    /// Keys not read by `T::from_hstore` are reported as `ExtraKeys`, once every field has been
+    /// read successfully.
+    pub fn into_struct<T: FromHstore>(self) -> Result<T, HstoreRecordError> {
+        let mut fields = HstoreFields { hstore: &self, used: HashSet::new() };
+        let value = T::from_hstore(&mut fields)?;
+
+        let mut extra: Vec<String> = self.keys()
+            .filter(|k| !fields.used.contains(*k))
+            .cloned()
+            .collect();
+        if !extra.is_empty() {
+            extra.sort();
+            return Err(HstoreRecordError::ExtraKeys(extra));
+        }
+        Ok(value)
+    }
+}
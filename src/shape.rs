@@ -0,0 +1,290 @@
+//! Validation of Hstore contents against an expected shape
+//!
+//! This is meant for checking configuration stored in an hstore when it is loaded.
+//!
+//! ```rust
+//! use diesel_pg_hstore::{Hstore, HstoreShape, HstoreShapeError};
+//! use diesel_pg_hstore::shape::ValueType;
+//!
+//! let shape = HstoreShape::new()
+//!     .required("host")
+//!     .value_type("port", ValueType::Int);
+//!
+//! let mut settings = Hstore::new();
+//! settings.insert("port".into(), "eighty".into());
+//!
+//! let errors = settings.validate_shape(&shape).unwrap_err();
+//! assert_eq!(errors, vec![
+//!     HstoreShapeError::MissingKey("host".into()),
+//!     HstoreShapeError::InvalidValue { key: "port".into(), expected: "an integer".into() },
+//! ]);
+//! ```
+
+use std::collections::HashMap;
+use std::error::Error as StdError;
+use std::fmt;
+
+#[cfg(feature = "regex")]
+use regex::Regex;
+
+use super::Hstore;
+
+/// The kind of value expected for a key
+#[derive(Debug, Clone)]
+pub enum ValueType {
+    /// A 64 bit signed integer
+    Int,
+    /// A boolean as Postgres reads it, such as `true`, `t`, `yes` or `0`, like `Hstore::as_flags`
+    Bool,
+    /// Any string but the empty one
+    NonEmptyString,
+    /// A string matching the regular expression, enabled by the `regex` feature
+    #[cfg(feature = "regex")]
+    Regex(Regex),
+}
+
+impl ValueType {
+    fn accepts(&self, value: &str) -> bool {
+        match *self {
+            ValueType::Int => value.parse::<i64>().is_ok(),
+            ValueType::Bool => parse_pg_bool(value).is_some(),
+            ValueType::NonEmptyString => !value.is_empty(),
+            #[cfg(feature = "regex")]
+            ValueType::Regex(ref re) => re.is_match(value),
+        }
+    }
+
+    fn expected(&self) -> String {
+        match *self {
+            ValueType::Int => "an integer".into(),
+            ValueType::Bool => "a boolean".into(),
+            ValueType::NonEmptyString => "a non-empty string".into(),
+            #[cfg(feature = "regex")]
+            ValueType::Regex(ref re) => format!("a match for {:?}", re.as_str()),
+        }
+    }
+}
+
+/// A coarse classification of a value, used to detect drift between two Hstores
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ValueKind {
+    /// Parses as a 64 bit signed integer
+    Int,
+    /// `true` or `false`
+    Bool,
+    /// Anything else
+    String,
+}
+
+impl ValueKind {
+    /// Classify a value, trying an integer first, then a boolean
+    pub fn of(value: &str) -> ValueKind {
+        if value.parse::<i64>().is_ok() {
+            ValueKind::Int
+        } else if value.parse::<bool>().is_ok() {
+            ValueKind::Bool
+        } else {
+            ValueKind::String
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Rule {
+    Required(String),
+    ValueType(String, ValueType),
+}
+
+/// The keys an Hstore must have and the values they may hold
+///
+/// Rules are checked in the order they were added. A key having a value type but not marked as
+/// required may be absent.
+#[derive(Debug, Clone, Default)]
+pub struct HstoreShape {
+    rules: Vec<Rule>,
+}
+
+impl HstoreShape {
+    /// Create a shape accepting any Hstore
+    pub fn new() -> HstoreShape {
+        HstoreShape::default()
+    }
+
+    /// Require `key` to be present
+    pub fn required(mut self, key: &str) -> HstoreShape {
+        self.rules.push(Rule::Required(key.to_string()));
+        self
+    }
+
+    /// Require the value for `key`, when present, to be of `value_type`
+    pub fn value_type(mut self, key: &str, value_type: ValueType) -> HstoreShape {
+        self.rules.push(Rule::ValueType(key.to_string(), value_type));
+        self
+    }
+}
+
+/// A violation of an `HstoreShape`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HstoreShapeError {
+    /// A required key is absent
+    MissingKey(String),
+    /// The value for a key is not of the expected type
+    InvalidValue {
+        /// The key holding the value
+        key: String,
+        /// A description of the expected value, such as `"an integer"`
+        expected: String,
+    },
+}
+
+impl fmt::Display for HstoreShapeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            HstoreShapeError::MissingKey(ref key) => write!(f, "missing key {:?}", key),
+            HstoreShapeError::InvalidValue { ref key, ref expected } => {
+                write!(f, "expected {} for key {:?}", expected, key)
+            }
+        }
+    }
+}
+
+impl StdError for HstoreShapeError {
+    fn description(&self) -> &str {
+        match *self {
+            HstoreShapeError::MissingKey(_) => "missing key",
+            HstoreShapeError::InvalidValue { .. } => "invalid value",
+        }
+    }
+}
+
+/// Error returned by `Hstore::as_flags` for a value which is not a boolean
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HstoreFlagError {
+    /// The key holding the value
+    pub key: String,
+    /// The value which could not be parsed
+    pub value: String,
+}
+
+impl fmt::Display for HstoreFlagError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "expected a boolean for key {:?}, found {:?}", self.key, self.value)
+    }
+}
+
+impl StdError for HstoreFlagError {
+    fn description(&self) -> &str {
+        "invalid flag value"
+    }
+}
+
+/// Parse a boolean the way Postgres does, ignoring case and surrounding whitespace
+fn parse_pg_bool(value: &str) -> Option<bool> {
+    match value.trim().to_lowercase().as_str() {
+        "t" | "true" | "y" | "yes" | "on" | "1" => Some(true),
+        "f" | "false" | "n" | "no" | "off" | "0" => Some(false),
+        _ => None,
+    }
+}
+
+impl Hstore {
+    /// Parse every value as a Postgres style boolean, for hstores holding feature flags
+    ///
+    /// `t`, `true`, `y`, `yes`, `on` and `1` are true, and `f`, `false`, `n`, `no`, `off` and `0`
+    /// are false, ignoring case and surrounding whitespace. When several values are invalid, the
+    /// error reports the smallest key.
+    ///
+    /// ```rust
+    /// use diesel_pg_hstore::Hstore;
+    ///
+    /// let mut flags = Hstore::new();
+    /// flags.insert("beta".into(), "t".into());
+    /// flags.insert("dark_mode".into(), "false".into());
+    ///
+    /// let flags = flags.as_flags().unwrap();
+    /// assert_eq!(flags["beta"], true);
+    /// assert_eq!(flags["dark_mode"], false);
+    /// ```
+    pub fn as_flags(&self) -> Result<HashMap<String, bool>, HstoreFlagError> {
+        let mut flags = HashMap::with_capacity(self.len());
+        let mut error: Option<HstoreFlagError> = None;
+        for (k, v) in self.iter() {
+            match parse_pg_bool(v) {
+                Some(flag) => {
+                    flags.insert(k.clone(), flag);
+                }
+                None => {
+                    if error.as_ref().map_or(true, |e| *k < e.key) {
+                        error = Some(HstoreFlagError { key: k.clone(), value: v.clone() });
+                    }
+                }
+            }
+        }
+
+        match error {
+            Some(error) => Err(error),
+            None => Ok(flags),
+        }
+    }
+
+    /// Check the Hstore against `shape`, returning every violation found
+    pub fn validate_shape(&self, shape: &HstoreShape) -> Result<(), Vec<HstoreShapeError>> {
+        let mut errors = Vec::new();
+        for rule in &shape.rules {
+            match *rule {
+                Rule::Required(ref key) => {
+                    if !self.contains_key(key) {
+                        errors.push(HstoreShapeError::MissingKey(key.clone()));
+                    }
+                }
+                Rule::ValueType(ref key, ref value_type) => {
+                    if let Some(value) = self.get(key) {
+                        if !value_type.accepts(value) {
+                            errors.push(HstoreShapeError::InvalidValue {
+                                key: key.clone(),
+                                expected: value_type.expected(),
+                            });
+                        }
+                    }
+                }
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// The keys present in both Hstores whose values are classified differently by `classify`
+    ///
+    /// Keys only present on one side are not reported. The keys are returned sorted.
+    ///
+    /// ```rust
+    /// use diesel_pg_hstore::Hstore;
+    /// use diesel_pg_hstore::shape::ValueKind;
+    ///
+    /// let mut before = Hstore::new();
+    /// before.insert("retries".into(), "5".into());
+    /// before.insert("name".into(), "app".into());
+    /// let mut after = Hstore::new();
+    /// after.insert("retries".into(), "five".into());
+    /// after.insert("name".into(), "web".into());
+    ///
+    /// assert_eq!(before.type_drift(&after, ValueKind::of), vec!["retries".to_string()]);
+    /// ```
+    pub fn type_drift<F>(&self, other: &Hstore, classify: F) -> Vec<String>
+        where F: Fn(&str) -> ValueKind
+    {
+        let mut keys: Vec<String> = self.iter()
+            .filter(|&(k, v)| match other.get(k) {
+                Some(o) => classify(v) != classify(o),
+                None => false,
+            })
+            .map(|(k, _)| k.clone())
+            .collect();
+        keys.sort();
+        keys
+    }
+}
@@ -0,0 +1,285 @@
+//! Hstore wrappers enforcing extra invariants on their entries
+
+use std::collections::HashMap;
+use std::error::Error as StdError;
+use std::fmt;
+use std::io::Write;
+use std::ops::Deref;
+
+use diesel::Queryable;
+use diesel::expression::AsExpression;
+use diesel::expression::bound::Bound;
+use diesel::pg::Pg;
+use diesel::row::Row;
+use diesel::types::{FromSql, FromSqlRow, IsNull, ToSql, ToSqlOutput};
+
+use super::Hstore;
+
+/// An Hstore which trims its keys and refuses keys colliding once trimmed
+///
+/// This catches keys that only differ by surrounding whitespace, such as `"key "` and `"key"`,
+/// at insert time. Inserting again under the exact same key still updates the value.
+///
+/// ```rust
+/// use diesel_pg_hstore::StrictHstore;
+///
+/// let mut settings = StrictHstore::new();
+/// settings.insert("key ", "1").unwrap();
+/// settings.insert("key ", "2").unwrap();
+///
+/// assert_eq!(settings["key"], "2");
+/// assert!(settings.insert("key", "3").is_err());
+/// ```
+///
+/// Postgres allows empty keys, and so does a StrictHstore by default. Use `reject_empty_keys` to
+/// have it fail with an `EmptyKeyError` when written to the database while holding an empty key.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StrictHstore {
+    hstore: Hstore,
+    /// The keys as they were given, by trimmed key
+    raw_keys: HashMap<String, String>,
+    reject_empty_keys: bool,
+}
+
+/// Error returned when inserting a key which collides with a different key of a `StrictHstore`
+/// once trimmed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DuplicateKeyError {
+    /// The trimmed key
+    pub key: String,
+}
+
+impl fmt::Display for DuplicateKeyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "duplicate hstore key {:?}", self.key)
+    }
+}
+
+impl StdError for DuplicateKeyError {
+    fn description(&self) -> &str {
+        "duplicate hstore key"
+    }
+}
+
+/// Error returned when writing a `StrictHstore` holding an empty key, if it rejects them
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EmptyKeyError;
+
+impl fmt::Display for EmptyKeyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.description())
+    }
+}
+
+impl StdError for EmptyKeyError {
+    fn description(&self) -> &str {
+        "empty hstore key"
+    }
+}
+
+/// You can deref the StrictHstore into the Hstore it wraps
+impl Deref for StrictHstore {
+    type Target = Hstore;
+
+    fn deref(&self) -> &Hstore {
+        &self.hstore
+    }
+}
+
+impl StrictHstore {
+    /// Create a new StrictHstore object
+    pub fn new() -> StrictHstore {
+        StrictHstore::default()
+    }
+
+    /// Set whether writing to the database fails when a key is empty
+    ///
+    /// ```rust
+    /// use diesel_pg_hstore::StrictHstore;
+    ///
+    /// let mut settings = StrictHstore::new().reject_empty_keys(true);
+    /// settings.insert(" ", "1").unwrap();
+    /// assert!(settings.has_empty_key());
+    /// ```
+    pub fn reject_empty_keys(mut self, reject: bool) -> StrictHstore {
+        self.reject_empty_keys = reject;
+        self
+    }
+
+    /// Check if any key is empty
+    pub fn has_empty_key(&self) -> bool {
+        self.hstore.contains_key("")
+    }
+
+    /// Insert an entry under the trimmed key
+    ///
+    /// The value is replaced when the key was inserted before exactly as given. Fails without
+    /// modifying the StrictHstore if a different key was inserted which trims to the same key.
+    pub fn insert(&mut self, k: &str, v: &str) -> Result<(), DuplicateKeyError> {
+        let trimmed = k.trim();
+        if let Some(raw) = self.raw_keys.get(trimmed) {
+            if raw != k {
+                return Err(DuplicateKeyError { key: trimmed.to_string() });
+            }
+        }
+
+        self.raw_keys.insert(trimmed.to_string(), k.to_string());
+        self.hstore.insert(trimmed.to_string(), v.to_string());
+        Ok(())
+    }
+
+    /// Remove an entry by its trimmed key
+    pub fn remove(&mut self, k: &str) -> Option<String> {
+        self.raw_keys.remove(k.trim());
+        self.hstore.remove(k.trim())
+    }
+
+    /// Unwrap into the inner Hstore
+    pub fn into_inner(self) -> Hstore {
+        self.hstore
+    }
+}
+
+impl<'a> AsExpression<Hstore> for &'a StrictHstore {
+    type Expression = Bound<Hstore, &'a StrictHstore>;
+
+    fn as_expression(self) -> Self::Expression {
+        Bound::new(self)
+    }
+}
+
+impl ToSql<Hstore, Pg> for StrictHstore {
+    fn to_sql<W>(&self, out: &mut ToSqlOutput<W, Pg>) -> Result<IsNull, Box<StdError + Send + Sync>>
+        where W: Write
+    {
+        if self.reject_empty_keys && self.has_empty_key() {
+            return Err(Box::new(EmptyKeyError));
+        }
+        ToSql::<Hstore, Pg>::to_sql(&self.hstore, out)
+    }
+}
+
+/// An Hstore which never holds an empty value
+///
+/// Inserting an empty value fails, and so does reading an hstore holding one from the database,
+/// so code receiving a NonEmptyHstore can rely on every value being non-empty. It is written to
+/// the database exactly like an `Hstore`.
+///
+/// ```rust
+/// use diesel_pg_hstore::NonEmptyHstore;
+///
+/// let mut settings = NonEmptyHstore::new();
+/// settings.insert("theme", "dark").unwrap();
+///
+/// assert_eq!(settings["theme"], "dark");
+/// assert!(settings.insert("font", "").is_err());
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NonEmptyHstore(Hstore);
+
+/// Error returned when an empty value is inserted into or read for a `NonEmptyHstore`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EmptyValueError {
+    /// The key of the empty value
+    pub key: String,
+}
+
+impl fmt::Display for EmptyValueError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "empty hstore value for key {:?}", self.key)
+    }
+}
+
+impl StdError for EmptyValueError {
+    fn description(&self) -> &str {
+        "empty hstore value"
+    }
+}
+
+/// You can deref the NonEmptyHstore into the Hstore it wraps
+impl Deref for NonEmptyHstore {
+    type Target = Hstore;
+
+    fn deref(&self) -> &Hstore {
+        &self.0
+    }
+}
+
+impl NonEmptyHstore {
+    /// Create a new NonEmptyHstore object
+    pub fn new() -> NonEmptyHstore {
+        NonEmptyHstore::default()
+    }
+
+    /// Wrap an Hstore, failing if any value is empty
+    ///
+    /// When several values are empty, the error reports the smallest key.
+    pub fn from_hstore(hstore: Hstore) -> Result<NonEmptyHstore, EmptyValueError> {
+        let empty = hstore.iter()
+            .filter(|&(_, v)| v.is_empty())
+            .map(|(k, _)| k)
+            .min();
+        match empty {
+            Some(k) => Err(EmptyValueError { key: k.clone() }),
+            None => Ok(NonEmptyHstore(hstore)),
+        }
+    }
+
+    /// Insert an entry, returning the previous value if any
+    ///
+    /// Fails without modifying the NonEmptyHstore if the value is empty.
+    pub fn insert(&mut self, k: &str, v: &str) -> Result<Option<String>, EmptyValueError> {
+        if v.is_empty() {
+            return Err(EmptyValueError { key: k.to_string() });
+        }
+
+        Ok(self.0.insert(k.to_string(), v.to_string()))
+    }
+
+    /// Remove an entry
+    pub fn remove(&mut self, k: &str) -> Option<String> {
+        self.0.remove(k)
+    }
+
+    /// Unwrap into the inner Hstore
+    pub fn into_inner(self) -> Hstore {
+        self.0
+    }
+}
+
+impl<'a> AsExpression<Hstore> for &'a NonEmptyHstore {
+    type Expression = Bound<Hstore, &'a NonEmptyHstore>;
+
+    fn as_expression(self) -> Self::Expression {
+        Bound::new(self)
+    }
+}
+
+impl ToSql<Hstore, Pg> for NonEmptyHstore {
+    fn to_sql<W>(&self, out: &mut ToSqlOutput<W, Pg>) -> Result<IsNull, Box<StdError + Send + Sync>>
+        where W: Write
+    {
+        ToSql::<Hstore, Pg>::to_sql(&self.0, out)
+    }
+}
+
+impl FromSql<Hstore, Pg> for NonEmptyHstore {
+    fn from_sql(bytes: Option<&[u8]>) -> Result<Self, Box<StdError + Send + Sync>> {
+        let hstore = Hstore::from_sql(bytes)?;
+        Ok(NonEmptyHstore::from_hstore(hstore)?)
+    }
+}
+
+impl FromSqlRow<Hstore, Pg> for NonEmptyHstore {
+    fn build_from_row<T: Row<Pg>>(row: &mut T) -> Result<Self, Box<StdError + Send + Sync>> {
+        NonEmptyHstore::from_sql(row.take())
+    }
+}
+
+impl Queryable<Hstore, Pg> for NonEmptyHstore {
+    type Row = Self;
+
+    fn build(row: Self::Row) -> Self {
+        row
+    }
+}
@@ -0,0 +1,640 @@
+//! Conversions between Hstore and flat structs, enabled by the `serde` feature
+
+use std::collections::HashMap;
+use std::error::Error as StdError;
+use std::fmt;
+
+use serde::de::{self, Deserialize, DeserializeOwned, Deserializer, IntoDeserializer, MapAccess,
+                Unexpected, Visitor};
+use serde::ser::{self, Impossible, Serialize, SerializeStruct, Serializer};
+
+use super::{Hstore, HstoreNullable, KeyValue, NonEmptyHstore};
+
+/// Error returned when converting between an Hstore and a struct
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HstoreSerdeError {
+    /// The value is not a struct or a map
+    NotAMap,
+    /// The value for this key is nested or a sequence, which a flat hstore can not hold
+    NestedValue(String),
+    /// A map key is not a string or a scalar
+    InvalidKey,
+    /// An error reported by a `Serialize` or `Deserialize` implementation
+    Custom(String),
+}
+
+impl fmt::Display for HstoreSerdeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            HstoreSerdeError::NestedValue(ref key) => {
+                write!(f, "{} for key {:?}", self.description(), key)
+            }
+            HstoreSerdeError::Custom(ref msg) => f.write_str(msg),
+            _ => f.write_str(self.description()),
+        }
+    }
+}
+
+impl StdError for HstoreSerdeError {
+    fn description(&self) -> &str {
+        match *self {
+            HstoreSerdeError::NotAMap => "expected a struct or a map",
+            HstoreSerdeError::NestedValue(_) => "nested value",
+            HstoreSerdeError::InvalidKey => "map key is not a string",
+            HstoreSerdeError::Custom(ref msg) => msg,
+        }
+    }
+}
+
+impl ser::Error for HstoreSerdeError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        HstoreSerdeError::Custom(msg.to_string())
+    }
+}
+
+impl de::Error for HstoreSerdeError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        HstoreSerdeError::Custom(msg.to_string())
+    }
+}
+
+impl Hstore {
+    /// Create an Hstore from a flat struct or map, without going through an intermediate format
+    ///
+    /// Every scalar field is stored as its text. Fields which are `None` or `()` are left out.
+    /// Nested structs, maps and sequences are refused.
+    ///
+    /// ```rust
+    /// # extern crate diesel_pg_hstore;
+    /// # #[macro_use] extern crate serde_derive;
+    /// use diesel_pg_hstore::Hstore;
+    ///
+    /// #[derive(Serialize)]
+    /// struct Settings {
+    ///     theme: &'static str,
+    ///     font_size: u32,
+    ///     beta: bool,
+    /// }
+    ///
+    /// # fn main() {
+    /// let settings = Settings { theme: "dark", font_size: 12, beta: true };
+    /// let hstore = Hstore::from_serialize(&settings).unwrap();
+    /// assert_eq!(hstore["font_size"], "12");
+    /// assert_eq!(hstore["beta"], "true");
+    /// # }
+    /// ```
+    pub fn from_serialize<T: Serialize>(value: &T) -> Result<Hstore, HstoreSerdeError> {
+        value.serialize(HstoreSerializer)
+    }
+
+    /// Create a struct or map from the entries, without going through an intermediate format
+    ///
+    /// Each value is parsed into the type of its field, so `"5"` becomes a `u32` and `"true"` a
+    /// `bool`. Keys missing from the Hstore deserialize into `None` for `Option` fields.
+    ///
+    /// ```rust
+    /// # extern crate diesel_pg_hstore;
+    /// # #[macro_use] extern crate serde_derive;
+    /// use diesel_pg_hstore::Hstore;
+    ///
+    /// #[derive(Deserialize)]
+    /// struct Settings {
+    ///     font_size: u32,
+    ///     beta: bool,
+    /// }
+    ///
+    /// # fn main() {
+    /// let mut hstore = Hstore::new();
+    /// hstore.insert("font_size".into(), "12".into());
+    /// hstore.insert("beta".into(), "true".into());
+    ///
+    /// let settings: Settings = hstore.to_deserialize().unwrap();
+    /// assert_eq!(settings.font_size, 12);
+    /// assert!(settings.beta);
+    /// # }
+    /// ```
+    pub fn to_deserialize<T: DeserializeOwned>(&self) -> Result<T, HstoreSerdeError> {
+        T::deserialize(HstoreDeserializer(self))
+    }
+}
+
+/// Serialize the entries as a plain map, such as `{"k": "v"}` in JSON
+impl Serialize for Hstore {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(self.iter())
+    }
+}
+
+/// Deserialize from a map of strings, such as `{"k": "v"}` in JSON
+impl<'de> Deserialize<'de> for Hstore {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Hstore, D::Error> {
+        HashMap::deserialize(deserializer).map(Hstore)
+    }
+}
+
+/// Serialize the entries as a plain map, the same as an Hstore
+impl Serialize for NonEmptyHstore {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        Hstore::serialize(self, serializer)
+    }
+}
+
+/// Serialize the entries as a plain map, with null values as `None`
+impl Serialize for HstoreNullable {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(self.iter())
+    }
+}
+
+/// Deserialize from a map of optional strings, such as `{"k": null}` in JSON
+impl<'de> Deserialize<'de> for HstoreNullable {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<HstoreNullable, D::Error> {
+        HashMap::deserialize(deserializer).map(HstoreNullable)
+    }
+}
+
+/// Serialize as a struct with `key` and `value` fields
+impl Serialize for KeyValue {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("KeyValue", 2)?;
+        state.serialize_field("key", &self.key)?;
+        state.serialize_field("value", &self.value)?;
+        state.end()
+    }
+}
+
+/// Deserialize from a struct with `key` and `value` fields
+impl<'de> Deserialize<'de> for KeyValue {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<KeyValue, D::Error> {
+        const FIELDS: &'static [&'static str] = &["key", "value"];
+        deserializer.deserialize_struct("KeyValue", FIELDS, KeyValueVisitor)
+    }
+}
+
+struct KeyValueVisitor;
+
+impl<'de> Visitor<'de> for KeyValueVisitor {
+    type Value = KeyValue;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a struct with key and value fields")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<KeyValue, A::Error> {
+        let mut key = None;
+        let mut value = None;
+        while let Some(field) = map.next_key::<String>()? {
+            match field.as_str() {
+                "key" => key = Some(map.next_value()?),
+                "value" => value = Some(map.next_value()?),
+                _ => {
+                    map.next_value::<de::IgnoredAny>()?;
+                }
+            }
+        }
+
+        match (key, value) {
+            (Some(key), Some(value)) => Ok(KeyValue { key: key, value: value }),
+            (None, _) => Err(de::Error::missing_field("key")),
+            (_, None) => Err(de::Error::missing_field("value")),
+        }
+    }
+}
+
+/// Serializes a struct or map into an Hstore
+struct HstoreSerializer;
+
+/// Collects the fields of a struct or map
+struct EntrySerializer {
+    hstore: Hstore,
+    key: Option<String>,
+}
+
+impl EntrySerializer {
+    fn insert<T: ?Sized + Serialize>(&mut self, key: String, value: &T) -> Result<(), HstoreSerdeError> {
+        match value.serialize(ValueSerializer) {
+            Ok(Some(value)) => {
+                self.hstore.insert(key, value);
+                Ok(())
+            }
+            Ok(None) => Ok(()),
+            Err(HstoreSerdeError::NestedValue(_)) => Err(HstoreSerdeError::NestedValue(key)),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+/// Serializes a scalar into its text, or `None` for values to leave out
+struct ValueSerializer;
+
+/// Serializes a map key into a string
+struct KeySerializer;
+
+macro_rules! refuse {
+    ($($method:ident($($arg:ty),*) -> $ret:ty;)*) => {
+        $(
+            fn $method(self, $(_: $arg),*) -> Result<$ret, HstoreSerdeError> {
+                Err(Self::refused())
+            }
+        )*
+    }
+}
+
+macro_rules! refuse_compound {
+    () => {
+        fn serialize_seq(self, _: Option<usize>) -> Result<Self::SerializeSeq, HstoreSerdeError> {
+            Err(Self::refused())
+        }
+
+        fn serialize_tuple(self, _: usize) -> Result<Self::SerializeTuple, HstoreSerdeError> {
+            Err(Self::refused())
+        }
+
+        fn serialize_tuple_struct(self, _: &'static str, _: usize)
+            -> Result<Self::SerializeTupleStruct, HstoreSerdeError>
+        {
+            Err(Self::refused())
+        }
+
+        fn serialize_tuple_variant(self, _: &'static str, _: u32, _: &'static str, _: usize)
+            -> Result<Self::SerializeTupleVariant, HstoreSerdeError>
+        {
+            Err(Self::refused())
+        }
+
+        fn serialize_struct_variant(self, _: &'static str, _: u32, _: &'static str, _: usize)
+            -> Result<Self::SerializeStructVariant, HstoreSerdeError>
+        {
+            Err(Self::refused())
+        }
+
+        fn serialize_newtype_variant<T: ?Sized + Serialize>(self, _: &'static str, _: u32,
+                                                            _: &'static str, _: &T)
+            -> Result<Self::Ok, HstoreSerdeError>
+        {
+            Err(Self::refused())
+        }
+    }
+}
+
+macro_rules! scalars_to_string {
+    ($($method:ident($ty:ty);)*) => {
+        $(
+            fn $method(self, v: $ty) -> Result<Self::Ok, HstoreSerdeError> {
+                Ok(v.to_string().into())
+            }
+        )*
+    }
+}
+
+impl HstoreSerializer {
+    fn refused() -> HstoreSerdeError {
+        HstoreSerdeError::NotAMap
+    }
+}
+
+impl Serializer for HstoreSerializer {
+    type Ok = Hstore;
+    type Error = HstoreSerdeError;
+    type SerializeSeq = Impossible<Hstore, HstoreSerdeError>;
+    type SerializeTuple = Impossible<Hstore, HstoreSerdeError>;
+    type SerializeTupleStruct = Impossible<Hstore, HstoreSerdeError>;
+    type SerializeTupleVariant = Impossible<Hstore, HstoreSerdeError>;
+    type SerializeMap = EntrySerializer;
+    type SerializeStruct = EntrySerializer;
+    type SerializeStructVariant = Impossible<Hstore, HstoreSerdeError>;
+
+    refuse! {
+        serialize_bool(bool) -> Hstore;
+        serialize_i8(i8) -> Hstore;
+        serialize_i16(i16) -> Hstore;
+        serialize_i32(i32) -> Hstore;
+        serialize_i64(i64) -> Hstore;
+        serialize_u8(u8) -> Hstore;
+        serialize_u16(u16) -> Hstore;
+        serialize_u32(u32) -> Hstore;
+        serialize_u64(u64) -> Hstore;
+        serialize_f32(f32) -> Hstore;
+        serialize_f64(f64) -> Hstore;
+        serialize_char(char) -> Hstore;
+        serialize_str(&str) -> Hstore;
+        serialize_bytes(&[u8]) -> Hstore;
+        serialize_none() -> Hstore;
+        serialize_unit() -> Hstore;
+        serialize_unit_struct(&'static str) -> Hstore;
+        serialize_unit_variant(&'static str, u32, &'static str) -> Hstore;
+    }
+
+    refuse_compound!();
+
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<Hstore, HstoreSerdeError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(self, _: &'static str, value: &T)
+        -> Result<Hstore, HstoreSerdeError>
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<EntrySerializer, HstoreSerdeError> {
+        Ok(EntrySerializer {
+            hstore: Hstore::with_capacity(len.unwrap_or(0)),
+            key: None,
+        })
+    }
+
+    fn serialize_struct(self, _: &'static str, len: usize) -> Result<EntrySerializer, HstoreSerdeError> {
+        self.serialize_map(Some(len))
+    }
+}
+
+impl ser::SerializeMap for EntrySerializer {
+    type Ok = Hstore;
+    type Error = HstoreSerdeError;
+
+    fn serialize_key<T: ?Sized + Serialize>(&mut self, key: &T) -> Result<(), HstoreSerdeError> {
+        self.key = Some(key.serialize(KeySerializer)?);
+        Ok(())
+    }
+
+    fn serialize_value<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), HstoreSerdeError> {
+        let key = self.key.take().expect("serialize_key to be called before serialize_value");
+        self.insert(key, value)
+    }
+
+    fn end(self) -> Result<Hstore, HstoreSerdeError> {
+        Ok(self.hstore)
+    }
+}
+
+impl ser::SerializeStruct for EntrySerializer {
+    type Ok = Hstore;
+    type Error = HstoreSerdeError;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, key: &'static str, value: &T)
+        -> Result<(), HstoreSerdeError>
+    {
+        self.insert(key.to_string(), value)
+    }
+
+    fn end(self) -> Result<Hstore, HstoreSerdeError> {
+        Ok(self.hstore)
+    }
+}
+
+impl ValueSerializer {
+    fn refused() -> HstoreSerdeError {
+        HstoreSerdeError::NestedValue(String::new())
+    }
+}
+
+impl Serializer for ValueSerializer {
+    type Ok = Option<String>;
+    type Error = HstoreSerdeError;
+    type SerializeSeq = Impossible<Option<String>, HstoreSerdeError>;
+    type SerializeTuple = Impossible<Option<String>, HstoreSerdeError>;
+    type SerializeTupleStruct = Impossible<Option<String>, HstoreSerdeError>;
+    type SerializeTupleVariant = Impossible<Option<String>, HstoreSerdeError>;
+    type SerializeMap = Impossible<Option<String>, HstoreSerdeError>;
+    type SerializeStruct = Impossible<Option<String>, HstoreSerdeError>;
+    type SerializeStructVariant = Impossible<Option<String>, HstoreSerdeError>;
+
+    scalars_to_string! {
+        serialize_bool(bool);
+        serialize_i8(i8);
+        serialize_i16(i16);
+        serialize_i32(i32);
+        serialize_i64(i64);
+        serialize_u8(u8);
+        serialize_u16(u16);
+        serialize_u32(u32);
+        serialize_u64(u64);
+        serialize_f32(f32);
+        serialize_f64(f64);
+        serialize_char(char);
+        serialize_str(&str);
+    }
+
+    refuse! {
+        serialize_bytes(&[u8]) -> Option<String>;
+        serialize_map(Option<usize>) -> Self::SerializeMap;
+        serialize_struct(&'static str, usize) -> Self::SerializeStruct;
+    }
+
+    refuse_compound!();
+
+    fn serialize_none(self) -> Result<Option<String>, HstoreSerdeError> {
+        Ok(None)
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<Option<String>, HstoreSerdeError> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Option<String>, HstoreSerdeError> {
+        Ok(None)
+    }
+
+    fn serialize_unit_struct(self, _: &'static str) -> Result<Option<String>, HstoreSerdeError> {
+        Ok(None)
+    }
+
+    fn serialize_unit_variant(self, _: &'static str, _: u32, variant: &'static str)
+        -> Result<Option<String>, HstoreSerdeError>
+    {
+        Ok(Some(variant.to_string()))
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(self, _: &'static str, value: &T)
+        -> Result<Option<String>, HstoreSerdeError>
+    {
+        value.serialize(self)
+    }
+}
+
+impl KeySerializer {
+    fn refused() -> HstoreSerdeError {
+        HstoreSerdeError::InvalidKey
+    }
+}
+
+impl Serializer for KeySerializer {
+    type Ok = String;
+    type Error = HstoreSerdeError;
+    type SerializeSeq = Impossible<String, HstoreSerdeError>;
+    type SerializeTuple = Impossible<String, HstoreSerdeError>;
+    type SerializeTupleStruct = Impossible<String, HstoreSerdeError>;
+    type SerializeTupleVariant = Impossible<String, HstoreSerdeError>;
+    type SerializeMap = Impossible<String, HstoreSerdeError>;
+    type SerializeStruct = Impossible<String, HstoreSerdeError>;
+    type SerializeStructVariant = Impossible<String, HstoreSerdeError>;
+
+    scalars_to_string! {
+        serialize_bool(bool);
+        serialize_i8(i8);
+        serialize_i16(i16);
+        serialize_i32(i32);
+        serialize_i64(i64);
+        serialize_u8(u8);
+        serialize_u16(u16);
+        serialize_u32(u32);
+        serialize_u64(u64);
+        serialize_f32(f32);
+        serialize_f64(f64);
+        serialize_char(char);
+        serialize_str(&str);
+    }
+
+    refuse! {
+        serialize_bytes(&[u8]) -> String;
+        serialize_none() -> String;
+        serialize_unit() -> String;
+        serialize_unit_struct(&'static str) -> String;
+        serialize_map(Option<usize>) -> Self::SerializeMap;
+        serialize_struct(&'static str, usize) -> Self::SerializeStruct;
+    }
+
+    refuse_compound!();
+
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<String, HstoreSerdeError> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit_variant(self, _: &'static str, _: u32, variant: &'static str)
+        -> Result<String, HstoreSerdeError>
+    {
+        Ok(variant.to_string())
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(self, _: &'static str, value: &T)
+        -> Result<String, HstoreSerdeError>
+    {
+        value.serialize(self)
+    }
+}
+
+/// Deserializes an Hstore as a map of its entries
+struct HstoreDeserializer<'a>(&'a Hstore);
+
+/// Walks the entries of an Hstore
+struct EntryAccess<'a, I> {
+    entries: I,
+    value: Option<&'a str>,
+}
+
+/// Deserializes a value by parsing it into the requested type
+struct ValueDeserializer<'a>(&'a str);
+
+impl<'de> Deserializer<'de> for HstoreDeserializer<'de> {
+    type Error = HstoreSerdeError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, HstoreSerdeError> {
+        visitor.visit_map(EntryAccess {
+            entries: self.0.iter(),
+            value: None,
+        })
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, HstoreSerdeError> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(self, _: &'static str, visitor: V)
+        -> Result<V::Value, HstoreSerdeError>
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 u8 u16 u32 u64 f32 f64 char str string bytes byte_buf unit
+        unit_struct seq tuple tuple_struct map struct enum identifier ignored_any
+    }
+}
+
+impl<'de, I> MapAccess<'de> for EntryAccess<'de, I>
+    where I: Iterator<Item = (&'de String, &'de String)>
+{
+    type Error = HstoreSerdeError;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, HstoreSerdeError>
+        where K: de::DeserializeSeed<'de>
+    {
+        match self.entries.next() {
+            Some((k, v)) => {
+                self.value = Some(v);
+                seed.deserialize(k.as_str().into_deserializer()).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, HstoreSerdeError>
+        where V: de::DeserializeSeed<'de>
+    {
+        let value = self.value.take().expect("next_key_seed to be called before next_value_seed");
+        seed.deserialize(ValueDeserializer(value))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        self.entries.size_hint().1
+    }
+}
+
+macro_rules! parse_value {
+    ($($method:ident => $visit:ident,)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, HstoreSerdeError> {
+                match self.0.parse() {
+                    Ok(v) => visitor.$visit(v),
+                    Err(_) => Err(de::Error::invalid_value(Unexpected::Str(self.0), &visitor)),
+                }
+            }
+        )*
+    }
+}
+
+impl<'de> Deserializer<'de> for ValueDeserializer<'de> {
+    type Error = HstoreSerdeError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, HstoreSerdeError> {
+        visitor.visit_borrowed_str(self.0)
+    }
+
+    parse_value! {
+        deserialize_bool => visit_bool,
+        deserialize_i8 => visit_i8,
+        deserialize_i16 => visit_i16,
+        deserialize_i32 => visit_i32,
+        deserialize_i64 => visit_i64,
+        deserialize_u8 => visit_u8,
+        deserialize_u16 => visit_u16,
+        deserialize_u32 => visit_u32,
+        deserialize_u64 => visit_u64,
+        deserialize_f32 => visit_f32,
+        deserialize_f64 => visit_f64,
+        deserialize_char => visit_char,
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, HstoreSerdeError> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(self, _: &'static str, visitor: V)
+        -> Result<V::Value, HstoreSerdeError>
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(self, _: &'static str, _: &'static [&'static str],
+                                         visitor: V)
+        -> Result<V::Value, HstoreSerdeError>
+    {
+        visitor.visit_enum(self.0.into_deserializer())
+    }
+
+    forward_to_deserialize_any! {
+        str string bytes byte_buf unit unit_struct seq tuple tuple_struct map struct identifier
+        ignored_any
+    }
+}
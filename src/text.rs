@@ -0,0 +1,406 @@
+//! Parsing of the hstore text representation
+
+use std::collections::BTreeSet;
+use std::error::Error as StdError;
+use std::fmt;
+use std::iter::Peekable;
+use std::str::{CharIndices, FromStr};
+
+use super::{needs_quoting, Hstore, HstoreNullable};
+
+/// Error returned when parsing malformed hstore text or `.env` style lines
+///
+/// Each variant carries the byte offset in the input at which the problem was found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HstoreParseError {
+    /// A double quoted token is missing its closing quote
+    UnterminatedQuote(usize),
+    /// The input ended where a key or value was expected
+    UnexpectedEnd(usize),
+    /// A key or value is empty without being quoted
+    MissingToken(usize),
+    /// A key is not followed by `=>`
+    MissingArrow(usize),
+    /// An entry is followed by something other than `,`
+    TrailingGarbage(usize),
+    /// A `.env` style line has no `=`
+    MissingEquals(usize),
+    /// A quoted `.env` style value is followed by something other than a comment
+    UnexpectedCharacter(usize),
+}
+
+impl HstoreParseError {
+    /// The byte offset in the input at which the error was found
+    pub fn position(&self) -> usize {
+        match *self {
+            HstoreParseError::UnterminatedQuote(p) |
+            HstoreParseError::UnexpectedEnd(p) |
+            HstoreParseError::MissingToken(p) |
+            HstoreParseError::MissingArrow(p) |
+            HstoreParseError::TrailingGarbage(p) |
+            HstoreParseError::MissingEquals(p) |
+            HstoreParseError::UnexpectedCharacter(p) => p,
+        }
+    }
+}
+
+impl fmt::Display for HstoreParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} at position {}", self.description(), self.position())
+    }
+}
+
+impl StdError for HstoreParseError {
+    fn description(&self) -> &str {
+        match *self {
+            HstoreParseError::UnterminatedQuote(_) => "unterminated quote",
+            HstoreParseError::UnexpectedEnd(_) => "unexpected end of input",
+            HstoreParseError::MissingToken(_) => "missing key or value",
+            HstoreParseError::MissingArrow(_) => "expected \"=>\"",
+            HstoreParseError::TrailingGarbage(_) => "expected \",\"",
+            HstoreParseError::MissingEquals(_) => "expected \"=\"",
+            HstoreParseError::UnexpectedCharacter(_) => "unexpected character",
+        }
+    }
+}
+
+impl Hstore {
+    /// Parse the hstore text representation, as in `"a"=>"1", b=>2`
+    ///
+    /// This is the format Postgres uses for hstore values in text mode results and literals,
+    /// while `from_wire_bytes` decodes the binary format Diesel uses. Keys and values may be
+    /// double quoted, with `\` escaping the next character. Entries having a `NULL` value are
+    /// ignored.
+    ///
+    /// ```rust
+    /// use diesel_pg_hstore::Hstore;
+    ///
+    /// let settings = Hstore::from_text(r#""Hello"=>"World", a=>1, b=>NULL"#).unwrap();
+    /// assert_eq!(settings.len(), 2);
+    /// assert_eq!(settings["Hello"], "World");
+    /// assert_eq!(settings["a"], "1");
+    /// ```
+    pub fn from_text(s: &str) -> Result<Hstore, HstoreParseError> {
+        let mut hstore = Hstore::new();
+        for (k, v) in parse_pairs(s)? {
+            if let Some(v) = v {
+                hstore.insert(k, v);
+            }
+        }
+        Ok(hstore)
+    }
+
+    /// Render up to `max_keys` entries, sorted by key, on a single line for logging
+    ///
+    /// Entries are written as in the hstore text format, with a key or value only double quoted
+    /// when `needs_quoting` requires it, and `"` and `\` then escaped with a backslash. When
+    /// entries are left out, the summary ends with the number of omitted entries.
+    ///
+    /// ```rust
+    /// use diesel_pg_hstore::Hstore;
+    ///
+    /// let mut settings = Hstore::new();
+    /// settings.insert("a".into(), "1".into());
+    /// settings.insert("b".into(), "say \"hi\"".into());
+    /// settings.insert("c".into(), "3".into());
+    ///
+    /// assert_eq!(settings.summary(2), r#"a=>1, b=>"say \"hi\"", … (1 more)"#);
+    /// ```
+    pub fn summary(&self, max_keys: usize) -> String {
+        let mut keys: Vec<&String> = self.keys().collect();
+        keys.sort();
+
+        let mut out = String::new();
+        for (i, k) in keys.iter().take(max_keys).enumerate() {
+            if i > 0 {
+                out.push_str(", ");
+            }
+            write_entry(&mut out, k, &self[k.as_str()]).expect("writing to a String to succeed");
+        }
+
+        if keys.len() > max_keys {
+            if max_keys > 0 {
+                out.push_str(", ");
+            }
+            out.push_str(&format!("… ({} more)", keys.len() - max_keys));
+        }
+        out
+    }
+
+    /// Join every key and value with `sep`, sorted by key, for feeding to `to_tsvector`
+    ///
+    /// This is plain concatenation: there is no escaping, stemming or stop word handling, all of
+    /// which is left to the text search configuration in Postgres.
+    ///
+    /// ```rust
+    /// use diesel_pg_hstore::Hstore;
+    ///
+    /// let mut settings = Hstore::new();
+    /// settings.insert("color".into(), "dark blue".into());
+    /// settings.insert("size".into(), "large".into());
+    ///
+    /// assert_eq!(settings.to_search_text(' '), "color dark blue size large");
+    /// ```
+    pub fn to_search_text(&self, sep: char) -> String {
+        let entries = self.entries_by_key();
+
+        let mut out = String::new();
+        for (i, (k, v)) in entries.into_iter().enumerate() {
+            if i > 0 {
+                out.push(sep);
+            }
+            out.push_str(k);
+            out.push(sep);
+            out.push_str(v);
+        }
+        out
+    }
+
+    /// Describe the changes from this Hstore to `other` for human review, one line per key
+    ///
+    /// Added keys are marked with `+`, removed ones with `-` and changed ones with `~`, showing
+    /// the old and new values. Keys and values are quoted and escaped like Rust strings, and the
+    /// lines are sorted by key. Unchanged keys are left out, so identical Hstores give an empty
+    /// report.
+    ///
+    /// ```rust
+    /// use diesel_pg_hstore::Hstore;
+    ///
+    /// let mut old = Hstore::new();
+    /// old.insert("a".into(), "1".into());
+    /// old.insert("b".into(), "2".into());
+    /// let mut new = Hstore::new();
+    /// new.insert("b".into(), "3".into());
+    /// new.insert("c".into(), "4".into());
+    ///
+    /// assert_eq!(old.diff_report(&new), r#"- "a" = "1"
+    /// ~ "b" = "2" → "3"
+    /// + "c" = "4"
+    /// "#);
+    /// ```
+    pub fn diff_report(&self, other: &Hstore) -> String {
+        let keys: BTreeSet<&String> = self.keys().chain(other.keys()).collect();
+
+        let mut out = String::new();
+        for k in keys {
+            match (self.get(k), other.get(k)) {
+                (Some(old), Some(new)) if old != new => {
+                    out.push_str(&format!("~ {:?} = {:?} → {:?}\n", k, old, new))
+                }
+                (Some(old), None) => out.push_str(&format!("- {:?} = {:?}\n", k, old)),
+                (None, Some(new)) => out.push_str(&format!("+ {:?} = {:?}\n", k, new)),
+                _ => {}
+            }
+        }
+        out
+    }
+}
+
+/// Parse the hstore text representation, the same as `Hstore::from_text`
+///
+/// ```rust
+/// use diesel_pg_hstore::Hstore;
+///
+/// let settings: Hstore = "a=>1, b=>NULL".parse().unwrap();
+/// assert_eq!(settings.len(), 1);
+/// ```
+impl FromStr for Hstore {
+    type Err = HstoreParseError;
+
+    fn from_str(s: &str) -> Result<Hstore, HstoreParseError> {
+        Hstore::from_text(s)
+    }
+}
+
+/// Parse the hstore text representation, keeping entries having a `NULL` value
+///
+/// ```rust
+/// use diesel_pg_hstore::HstoreNullable;
+///
+/// let settings: HstoreNullable = "a=>1, b=>NULL".parse().unwrap();
+/// assert_eq!(settings["a"], Some("1".into()));
+/// assert_eq!(settings["b"], None);
+/// ```
+impl FromStr for HstoreNullable {
+    type Err = HstoreParseError;
+
+    fn from_str(s: &str) -> Result<HstoreNullable, HstoreParseError> {
+        Ok(parse_pairs(s)?.into_iter().collect())
+    }
+}
+
+/// Render the hstore text representation, sorted by key
+///
+/// Keys and values are only double quoted when `needs_quoting` says so, with `"` and `\`
+/// escaped by a backslash, so the output is compact and parses back into the same Hstore.
+///
+/// ```rust
+/// use diesel_pg_hstore::Hstore;
+///
+/// let mut settings = Hstore::new();
+/// settings.insert("b c".into(), "say \"hi\"".into());
+/// settings.insert("a".into(), "1".into());
+///
+/// assert_eq!(settings.to_string(), r#"a=>1, "b c"=>"say \"hi\"""#);
+/// assert_eq!(settings.to_string().parse::<Hstore>().unwrap(), settings);
+/// ```
+impl fmt::Display for Hstore {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let entries = self.entries_by_key();
+
+        for (i, (k, v)) in entries.into_iter().enumerate() {
+            if i > 0 {
+                f.write_str(", ")?;
+            }
+            write_entry(f, k, v)?;
+        }
+        Ok(())
+    }
+}
+
+/// Write an entry in the hstore text format, quoting the key and value only when needed
+pub(crate) fn write_entry<W: fmt::Write>(out: &mut W, k: &str, v: &str) -> fmt::Result {
+    write_quoted(out, k)?;
+    out.write_str("=>")?;
+    write_quoted(out, v)
+}
+
+fn write_quoted<W: fmt::Write>(out: &mut W, s: &str) -> fmt::Result {
+    if !needs_quoting(s) {
+        return out.write_str(s);
+    }
+
+    out.write_char('"')?;
+    for c in s.chars() {
+        if c == '"' || c == '\\' {
+            out.write_char('\\')?;
+        }
+        out.write_char(c)?;
+    }
+    out.write_char('"')
+}
+
+/// Parse the hstore text representation into its entries, keeping null values
+pub(crate) fn parse_pairs(s: &str) -> Result<Vec<(String, Option<String>)>, HstoreParseError> {
+    let mut parser = Parser {
+        chars: s.char_indices().peekable(),
+        len: s.len(),
+    };
+    let mut pairs = Vec::new();
+
+    parser.skip_whitespace();
+    while !parser.at_end() {
+        let (key, _) = parser.token(Token::Key)?;
+
+        parser.skip_whitespace();
+        let arrow = parser.position();
+        if !parser.consume('=') || !parser.consume('>') {
+            return Err(HstoreParseError::MissingArrow(arrow));
+        }
+
+        parser.skip_whitespace();
+        let (value, quoted) = parser.token(Token::Value)?;
+        let value = if !quoted && value.eq_ignore_ascii_case("null") {
+            None
+        } else {
+            Some(value)
+        };
+        pairs.push((key, value));
+
+        parser.skip_whitespace();
+        if parser.at_end() {
+            break;
+        }
+        if !parser.consume(',') {
+            return Err(HstoreParseError::TrailingGarbage(parser.position()));
+        }
+        parser.skip_whitespace();
+    }
+
+    Ok(pairs)
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Token {
+    Key,
+    Value,
+}
+
+struct Parser<'a> {
+    chars: Peekable<CharIndices<'a>>,
+    len: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn position(&mut self) -> usize {
+        let len = self.len;
+        self.chars.peek().map(|&(i, _)| i).unwrap_or(len)
+    }
+
+    fn at_end(&mut self) -> bool {
+        self.chars.peek().is_none()
+    }
+
+    fn consume(&mut self, c: char) -> bool {
+        match self.chars.peek() {
+            Some(&(_, next)) if next == c => {
+                self.chars.next();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(&(_, c)) = self.chars.peek() {
+            if !c.is_whitespace() {
+                break;
+            }
+            self.chars.next();
+        }
+    }
+
+    /// Read a key or value, returning it along with whether it was quoted
+    fn token(&mut self, token: Token) -> Result<(String, bool), HstoreParseError> {
+        let start = self.position();
+        if self.at_end() {
+            return Err(HstoreParseError::UnexpectedEnd(start));
+        }
+
+        if self.consume('"') {
+            let mut s = String::new();
+            loop {
+                match self.chars.next() {
+                    Some((_, '"')) => return Ok((s, true)),
+                    Some((_, '\\')) => match self.chars.next() {
+                        Some((_, c)) => s.push(c),
+                        None => return Err(HstoreParseError::UnterminatedQuote(start)),
+                    },
+                    Some((_, c)) => s.push(c),
+                    None => return Err(HstoreParseError::UnterminatedQuote(start)),
+                }
+            }
+        }
+
+        let mut s = String::new();
+        while let Some(&(_, c)) = self.chars.peek() {
+            if c.is_whitespace() || (token == Token::Key && c == '=') ||
+                (token == Token::Value && c == ',') {
+                break;
+            }
+            self.chars.next();
+            if c == '\\' {
+                if let Some((_, escaped)) = self.chars.next() {
+                    s.push(escaped);
+                }
+            } else {
+                s.push(c);
+            }
+        }
+
+        if s.is_empty() {
+            return Err(HstoreParseError::MissingToken(start));
+        }
+        Ok((s, false))
+    }
+}
@@ -0,0 +1,47 @@
+//! Unicode normalization of hstore entries, enabled by the `unicode` feature
+
+use std::collections::hash_map::Entry;
+
+use unicode_normalization::UnicodeNormalization;
+
+use super::Hstore;
+
+impl Hstore {
+    /// Convert every key and value to Unicode Normalization Form C (NFC)
+    ///
+    /// Canonically equivalent strings can be encoded with different bytes, such as `"é"` written
+    /// as a single code point or as `"e"` followed by a combining accent. Such keys look the same
+    /// but do not match on lookup, in Rust or in Postgres, until they are normalized.
+    ///
+    /// Keys which normalize to the same form are merged into one entry. The value of the key that
+    /// was already in NFC is kept; if none of them was, which value is kept is unspecified.
+    ///
+    /// ```rust
+    /// use diesel_pg_hstore::Hstore;
+    ///
+    /// let mut settings = Hstore::new();
+    /// settings.insert("cafe\u{301}".into(), "open".into());
+    /// settings.normalize_unicode();
+    ///
+    /// assert_eq!(settings["caf\u{e9}"], "open");
+    /// ```
+    pub fn normalize_unicode(&mut self) {
+        let entries: Vec<(String, String)> = self.0.drain().collect();
+        for (k, v) in entries {
+            let key: String = k.nfc().collect();
+            let was_normalized = key == k;
+            let value: String = v.nfc().collect();
+
+            match self.0.entry(key) {
+                Entry::Occupied(mut e) => {
+                    if was_normalized {
+                        e.insert(value);
+                    }
+                }
+                Entry::Vacant(e) => {
+                    e.insert(value);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,56 @@
+extern crate diesel_pg_hstore;
+
+use diesel_pg_hstore::{Hstore, HstoreParseError};
+
+#[test]
+fn plain_values() {
+    let m = Hstore::from_env_string("A=1\r\nB = two words \nC=\nD=a#b").unwrap();
+
+    assert_eq!(m.len(), 4);
+    assert_eq!(m["A"], "1");
+    assert_eq!(m["B"], "two words");
+    assert_eq!(m["C"], "");
+    assert_eq!(m["D"], "a#b");
+}
+
+#[test]
+fn quoting() {
+    let m = Hstore::from_env_string(r#"
+DOUBLE="a \"quoted\" # value\tthere\\"
+SINGLE='literal \n # here'
+SPACES="  padded  "
+"#).unwrap();
+
+    assert_eq!(m.len(), 3);
+    assert_eq!(m["DOUBLE"], "a \"quoted\" # value\tthere\\");
+    assert_eq!(m["SINGLE"], "literal \\n # here");
+    assert_eq!(m["SPACES"], "  padded  ");
+}
+
+#[test]
+fn export_prefix() {
+    let m = Hstore::from_env_string("export A=1\n  export\tB=2\nexporter=3").unwrap();
+
+    assert_eq!(m.len(), 3);
+    assert_eq!(m["A"], "1");
+    assert_eq!(m["B"], "2");
+    assert_eq!(m["exporter"], "3");
+}
+
+#[test]
+fn comments() {
+    let m = Hstore::from_env_string("# comment\n\n  # indented=1\nA=1 # trailing\nB='2' # quoted").unwrap();
+
+    assert_eq!(m.len(), 2);
+    assert_eq!(m["A"], "1");
+    assert_eq!(m["B"], "2");
+}
+
+#[test]
+fn malformed() {
+    assert_eq!(Hstore::from_env_string("A=1\nB"), Err(HstoreParseError::MissingEquals(5)));
+    assert_eq!(Hstore::from_env_string("A=1\n =2"), Err(HstoreParseError::MissingToken(5)));
+    assert_eq!(Hstore::from_env_string("A=\"open"), Err(HstoreParseError::UnterminatedQuote(2)));
+    assert_eq!(Hstore::from_env_string("A='open"), Err(HstoreParseError::UnterminatedQuote(2)));
+    assert_eq!(Hstore::from_env_string("A=\"x\" y"), Err(HstoreParseError::UnexpectedCharacter(6)));
+}
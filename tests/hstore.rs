@@ -12,7 +12,9 @@ use diesel::Connection;
 use diesel::pg::PgConnection;
 use diesel::connection::SimpleConnection;
 
-use diesel_pg_hstore::Hstore;
+use diesel_pg_hstore::{Hstore, HstoreNullable, HstoreOpExtensions, NonEmptyHstore, StrictHstore};
+use diesel_pg_hstore::predicates::HstoreFilter;
+use diesel_pg_hstore::query_string::update_expr_from_query_string;
 
 fn connection() -> PgConnection {
     dotenv::dotenv().ok();
@@ -20,6 +22,14 @@ fn connection() -> PgConnection {
     PgConnection::establish(&database_url).unwrap()
 }
 
+/// A connection inside a transaction which is never committed, so tests recreating and filling
+/// `hstore_table` concurrently do not see each other's rows
+fn test_connection() -> PgConnection {
+    let db = connection();
+    db.begin_test_transaction().unwrap();
+    db
+}
+
 table! {
     use diesel::types::*;
     use diesel_pg_hstore::Hstore;
@@ -79,3 +89,923 @@ fn metadata() {
     assert_eq!(data[1].store["Hello"], "There".to_string());
     assert_eq!(data[1].store["Again"], "Stuff".to_string());
 }
+
+table! {
+    use diesel::types::*;
+    use diesel_pg_hstore::Hstore;
+
+    multi_hstore_table {
+        id -> Integer,
+        first -> Hstore,
+        second -> Hstore,
+    }
+}
+
+#[derive(Insertable, Queryable, Debug, PartialEq)]
+#[table_name = "multi_hstore_table"]
+struct HasManyHstores {
+    id: i32,
+    first: Hstore,
+    second: Hstore,
+}
+
+#[test]
+fn multiple_hstore_columns() {
+    let db = connection();
+    db.batch_execute(r#"
+        CREATE EXTENSION IF NOT EXISTS hstore;
+        DROP TABLE IF EXISTS multi_hstore_table;
+        CREATE TABLE multi_hstore_table (
+            id SERIAL PRIMARY KEY,
+            first hstore NOT NULL,
+            second hstore NOT NULL
+        );
+    "#).unwrap();
+
+    let mut first = Hstore::new();
+    first.insert("a".into(), "1".into());
+    let mut second = Hstore::new();
+    second.insert("b".into(), "2".into());
+    second.insert("c".into(), "3".into());
+
+    let row = HasManyHstores {
+        id: 1,
+        first: first,
+        second: second,
+    };
+
+    diesel::insert_into(multi_hstore_table::table)
+        .values(&row)
+        .execute(&db)
+        .expect("To insert data");
+
+    let data: HasManyHstores = multi_hstore_table::table
+        .get_result(&db)
+        .expect("To get data");
+
+    assert_eq!(data, row);
+}
+
+#[test]
+fn filter_by_value() {
+    let db = test_connection();
+    make_table(&db);
+
+    let mut m = Hstore::new();
+    m.insert("a".into(), "3".into());
+
+    diesel::insert_into(hstore_table::table)
+        .values(&HasHstore { id: 2, store: m })
+        .execute(&db)
+        .expect("To insert data");
+
+    let ids: Vec<i32> = hstore_table::table
+        .select(hstore_table::id)
+        .filter(hstore_table::store.get_value("a").eq("1"))
+        .load(&db)
+        .expect("To get data");
+    assert_eq!(ids, vec![1]);
+
+    let ids: Vec<i32> = hstore_table::table
+        .select(hstore_table::id)
+        .filter(hstore_table::store.value_eq("a", "3"))
+        .load(&db)
+        .expect("To get data");
+    assert_eq!(ids, vec![2]);
+
+    let ids: Vec<i32> = hstore_table::table
+        .select(hstore_table::id)
+        .filter(hstore_table::store.value_eq("missing", "1"))
+        .load(&db)
+        .expect("To get data");
+    assert!(ids.is_empty());
+}
+
+#[test]
+fn combined_filter() {
+    let db = test_connection();
+    make_table(&db);
+
+    let mut m = Hstore::new();
+    m.insert("a".into(), "1".into());
+    m.insert("b".into(), "3".into());
+
+    diesel::insert_into(hstore_table::table)
+        .values(&HasHstore { id: 2, store: m })
+        .execute(&db)
+        .expect("To insert data");
+
+    let all = HstoreFilter::all()
+        .condition(hstore_table::store.value_eq("a", "1"))
+        .condition(hstore_table::store.value_eq("b", "3"))
+        .build();
+    let ids: Vec<i32> = hstore_table::table
+        .select(hstore_table::id)
+        .filter(all)
+        .load(&db)
+        .expect("To get data");
+    assert_eq!(ids, vec![2]);
+
+    let any = HstoreFilter::any()
+        .condition(hstore_table::store.value_eq("b", "2"))
+        .condition(hstore_table::store.value_eq("b", "3"))
+        .build();
+    let ids: Vec<i32> = hstore_table::table
+        .select(hstore_table::id)
+        .filter(any)
+        .order(hstore_table::id)
+        .load(&db)
+        .expect("To get data");
+    assert_eq!(ids, vec![1, 2]);
+}
+
+#[test]
+fn extension_version() {
+    let db = test_connection();
+    make_table(&db);
+
+    let version = diesel_pg_hstore::hstore_extension_version(&db).expect("To get the version");
+    let mut parts = version.split('.');
+    assert!(parts.next().unwrap().parse::<u32>().is_ok());
+    assert!(parts.next().unwrap().parse::<u32>().is_ok());
+}
+
+#[test]
+fn contains_pair() {
+    let db = test_connection();
+    make_table(&db);
+
+    let ids: Vec<i32> = hstore_table::table
+        .select(hstore_table::id)
+        .filter(hstore_table::store.contains_pair("a", "1"))
+        .load(&db)
+        .expect("To get data");
+    assert_eq!(ids, vec![1]);
+
+    let ids: Vec<i32> = hstore_table::table
+        .select(hstore_table::id)
+        .filter(hstore_table::store.contains_pair("a", "2"))
+        .load(&db)
+        .expect("To get data");
+    assert!(ids.is_empty());
+}
+
+#[test]
+fn is_contained_pair() {
+    let db = test_connection();
+    make_table(&db);
+
+    let mut m = Hstore::new();
+    m.insert("a".into(), "1".into());
+
+    diesel::insert_into(hstore_table::table)
+        .values(&HasHstore { id: 2, store: m })
+        .execute(&db)
+        .expect("To insert data");
+
+    let ids: Vec<i32> = hstore_table::table
+        .select(hstore_table::id)
+        .filter(hstore_table::store.is_contained_pair("a", "1"))
+        .load(&db)
+        .expect("To get data");
+    assert_eq!(ids, vec![2]);
+
+    let ids: Vec<i32> = hstore_table::table
+        .select(hstore_table::id)
+        .filter(hstore_table::store.is_contained_pair("b", "2"))
+        .load(&db)
+        .expect("To get data");
+    assert!(ids.is_empty());
+}
+
+#[test]
+fn text_representation() {
+    use diesel::dsl::sql;
+    use diesel::types::Text;
+
+    let db = test_connection();
+    make_table(&db);
+
+    let mut m = Hstore::new();
+    m.insert("a b".into(), "1, \"2\"".into());
+    m.insert("c".into(), "".into());
+
+    diesel::insert_into(hstore_table::table)
+        .values(&HasHstore { id: 2, store: m.clone() })
+        .execute(&db)
+        .expect("To insert data");
+
+    let text: String = hstore_table::table
+        .select(sql::<Text>("store::text"))
+        .filter(hstore_table::id.eq(2))
+        .get_result(&db)
+        .expect("To get data");
+
+    assert_eq!(Hstore::from_text(&text).unwrap(), m);
+}
+
+#[test]
+fn value_like() {
+    let db = test_connection();
+    make_table(&db);
+
+    let mut m = Hstore::new();
+    m.insert("name".into(), "hello world".into());
+
+    diesel::insert_into(hstore_table::table)
+        .values(&HasHstore { id: 2, store: m })
+        .execute(&db)
+        .expect("To insert data");
+
+    let ids: Vec<i32> = hstore_table::table
+        .select(hstore_table::id)
+        .filter(hstore_table::store.value_like("name", "%lo wo%"))
+        .load(&db)
+        .expect("To get data");
+    assert_eq!(ids, vec![2]);
+
+    let ids: Vec<i32> = hstore_table::table
+        .select(hstore_table::id)
+        .filter(hstore_table::store.value_like("name", "%nope%"))
+        .load(&db)
+        .expect("To get data");
+    assert!(ids.is_empty());
+}
+
+#[test]
+fn update_from_query_string() {
+    let db = test_connection();
+    make_table(&db);
+
+    let changes = update_expr_from_query_string(hstore_table::store, "b=3&c=hello+world%21")
+        .expect("To parse the query string");
+    diesel::update(hstore_table::table.filter(hstore_table::id.eq(1)))
+        .set(changes)
+        .execute(&db)
+        .expect("To update data");
+
+    let data: HasHstore = hstore_table::table
+        .find(1)
+        .get_result(&db)
+        .expect("To get data");
+
+    assert_eq!(data.store.len(), 3);
+    assert_eq!(data.store["a"], "1");
+    assert_eq!(data.store["b"], "3");
+    assert_eq!(data.store["c"], "hello world!");
+}
+
+#[test]
+fn get_value_missing_key() {
+    let db = test_connection();
+    make_table(&db);
+
+    let values: Vec<(Option<String>, Option<String>)> = hstore_table::table
+        .select((hstore_table::store.get_value("a"), hstore_table::store.get_value("missing")))
+        .filter(hstore_table::id.eq(1))
+        .load(&db)
+        .expect("To get data");
+    assert_eq!(values, vec![(Some("1".to_string()), None)]);
+}
+
+#[test]
+fn get_values() {
+    let db = test_connection();
+    make_table(&db);
+
+    let values: Vec<Option<String>> = hstore_table::table
+        .select(hstore_table::store.get_values(vec!["b", "z", "a"]))
+        .filter(hstore_table::id.eq(1))
+        .get_result(&db)
+        .expect("To get data");
+    assert_eq!(values, vec![Some("2".to_string()), None, Some("1".to_string())]);
+
+    let values: Vec<Option<String>> = hstore_table::table
+        .select(hstore_table::store.get_array(vec!["z", "b"]))
+        .filter(hstore_table::id.eq(1))
+        .get_result(&db)
+        .expect("To get data");
+    assert_eq!(values, vec![None, Some("2".to_string())]);
+
+    let value: Option<String> = hstore_table::table
+        .select(hstore_table::store.get_text("a"))
+        .filter(hstore_table::id.eq(1))
+        .get_result(&db)
+        .expect("To get data");
+    assert_eq!(value, Some("1".to_string()));
+}
+
+#[test]
+fn set_key() {
+    let db = test_connection();
+    make_table(&db);
+
+    diesel::update(hstore_table::table)
+        .set(hstore_table::store.eq(hstore_table::store.set_key("a", "10")))
+        .execute(&db)
+        .expect("To update data");
+    diesel::update(hstore_table::table)
+        .set(hstore_table::store.eq(hstore_table::store.set_key("c", "3")))
+        .execute(&db)
+        .expect("To update data");
+
+    let store: Hstore = hstore_table::table
+        .select(hstore_table::store)
+        .filter(hstore_table::id.eq(1))
+        .get_result(&db)
+        .expect("To get data");
+    assert_eq!(store, Hstore::from_array([("a", "10"), ("b", "2"), ("c", "3")]));
+}
+
+#[test]
+fn remove_keys() {
+    let db = test_connection();
+    make_table(&db);
+
+    let keys: Vec<String> = "a,z".split(',').map(String::from).collect();
+
+    let query = diesel::update(hstore_table::table)
+        .set(hstore_table::store.eq(hstore_table::store.remove_keys(keys.clone())));
+    let sql = diesel::debug_query::<diesel::pg::Pg, _>(&query).to_string();
+    assert!(sql.contains(r#""store" = "hstore_table"."store" - $1"#), "{}", sql);
+
+    query.execute(&db).expect("To update data");
+
+    let store: Hstore = hstore_table::table
+        .select(hstore_table::store)
+        .filter(hstore_table::id.eq(1))
+        .get_result(&db)
+        .expect("To get data");
+    assert_eq!(store, Hstore::from_array([("b", "2")]));
+}
+
+#[test]
+fn get_value_or() {
+    let db = test_connection();
+    make_table(&db);
+
+    let values: (String, String) = hstore_table::table
+        .select((hstore_table::store.get_value_or("a", "none"),
+                 hstore_table::store.get_value_or("z", "none")))
+        .filter(hstore_table::id.eq(1))
+        .get_result(&db)
+        .expect("To get data");
+    assert_eq!(values, ("1".to_string(), "none".to_string()));
+}
+
+#[test]
+fn merged_matches_concat() {
+    use diesel::expression::AsExpression;
+    use diesel_pg_hstore::predicates::HstoreConcat;
+
+    let db = test_connection();
+
+    let left = Hstore::from_array([("a", "1"), ("b", "2")]);
+    let right = Hstore::from_array([("b", "20"), ("c", "30")]);
+
+    let concat = HstoreConcat::new(AsExpression::<Hstore>::as_expression(&left),
+                                   AsExpression::<Hstore>::as_expression(&right));
+    let concat: Hstore = diesel::select(concat)
+        .get_result(&db)
+        .expect("To get data");
+    assert_eq!(left.merged(right), concat);
+}
+
+#[test]
+fn delete_functions() {
+    let db = test_connection();
+    make_table(&db);
+    db.batch_execute("INSERT INTO hstore_table (id, store) VALUES (2, 'a=>1,b=>2,c=>3'::hstore);")
+        .unwrap();
+
+    let pairs = Hstore::from_array([("a", "1"), ("b", "9")]);
+    let query = hstore_table::table
+        .select((hstore_table::store.delete_key("a"),
+                 hstore_table::store.delete_keys(vec!["a", "c", "z"]),
+                 hstore_table::store.delete_matching(&pairs)))
+        .filter(hstore_table::id.eq(2));
+    let sql = diesel::debug_query::<diesel::pg::Pg, _>(&query).to_string();
+    assert!(sql.starts_with(r#"SELECT delete("hstore_table"."store", $1), delete("#), "{}", sql);
+
+    let (key, keys, matching): (Hstore, Hstore, Hstore) = query.get_result(&db)
+        .expect("To get data");
+    assert_eq!(key, Hstore::from_array([("b", "2"), ("c", "3")]));
+    assert_eq!(keys, Hstore::from_array([("b", "2")]));
+    assert_eq!(matching, Hstore::from_array([("b", "2"), ("c", "3")]));
+}
+
+#[test]
+fn case_expression() {
+    use diesel::dsl::sql;
+    use diesel::types::Nullable;
+
+    let db = test_connection();
+    make_table(&db);
+
+    let stores: Vec<Option<Hstore>> = hstore_table::table
+        .select(sql::<Nullable<Hstore>>("CASE WHEN store ? 'a' THEN store END"))
+        .load(&db)
+        .expect("To get data");
+    assert_eq!(stores.len(), 1);
+    assert_eq!(stores[0].as_ref().map(|s| s["b"].as_str()), Some("2"));
+
+    let stores: Vec<Hstore> = hstore_table::table
+        .select(sql::<Hstore>("CASE WHEN store ? 'z' THEN store ELSE 'z=>0'::hstore END"))
+        .load(&db)
+        .expect("To get data");
+    assert_eq!(stores[0].len(), 1);
+    assert_eq!(stores[0]["z"], "0");
+}
+
+#[test]
+fn union() {
+    use diesel::dsl::sql;
+    use diesel::types::Integer;
+
+    let db = test_connection();
+    make_table(&db);
+
+    let data: Vec<(i32, Hstore)> = sql::<(Integer, Hstore)>(r#"
+        SELECT id, store FROM hstore_table
+        UNION ALL
+        SELECT 2, 'c=>3'::hstore
+        ORDER BY 1
+    "#).load(&db)
+        .expect("To get data");
+
+    assert_eq!(data.len(), 2);
+    assert_eq!(data[0].1["a"], "1");
+    assert_eq!(data[1].0, 2);
+    assert_eq!(data[1].1["c"], "3");
+}
+
+#[test]
+fn equality_ignores_key_order() {
+    use diesel::dsl::sql;
+
+    let db = test_connection();
+    make_table(&db);
+
+    let ids: Vec<i32> = hstore_table::table
+        .select(hstore_table::id)
+        .filter(hstore_table::store.eq(sql::<Hstore>("'b=>2, a=>1'::hstore")))
+        .load(&db)
+        .expect("To get data");
+    assert_eq!(ids, vec![1]);
+
+    let mut m = Hstore::new();
+    m.insert("b".into(), "2".into());
+    m.insert("a".into(), "1".into());
+
+    let ids: Vec<i32> = hstore_table::table
+        .select(hstore_table::id)
+        .filter(hstore_table::store.eq(&m))
+        .load(&db)
+        .expect("To get data");
+    assert_eq!(ids, vec![1]);
+
+    m.insert("c".into(), "3".into());
+    let ids: Vec<i32> = hstore_table::table
+        .select(hstore_table::id)
+        .filter(hstore_table::store.eq(&m))
+        .load(&db)
+        .expect("To get data");
+    assert!(ids.is_empty());
+}
+
+#[test]
+fn slice_keys() {
+    let db = test_connection();
+    make_table(&db);
+
+    let slice: Hstore = hstore_table::table
+        .select(hstore_table::store.slice_keys(vec!["a", "z"]))
+        .filter(hstore_table::id.eq(1))
+        .get_result(&db)
+        .expect("To get data");
+
+    assert_eq!(slice.len(), 1);
+    assert_eq!(slice["a"], "1");
+}
+
+#[test]
+fn slice_keys_subset() {
+    let db = test_connection();
+    make_table(&db);
+    db.batch_execute("INSERT INTO hstore_table (id, store) VALUES (2, 'a=>1,b=>2,c=>3'::hstore);")
+        .unwrap();
+
+    let slice: Hstore = hstore_table::table
+        .select(hstore_table::store.slice_keys(vec!["a", "c"]))
+        .filter(hstore_table::id.eq(2))
+        .get_result(&db)
+        .expect("To get data");
+
+    assert_eq!(slice, Hstore::from_array([("a", "1"), ("c", "3")]));
+    assert!(!slice.contains_key("b"));
+}
+
+#[test]
+fn strict_empty_key_pass_through() {
+    let db = test_connection();
+    make_table(&db);
+
+    let mut m = StrictHstore::new();
+    m.insert("  ", "blank").unwrap();
+
+    diesel::insert_into(hstore_table::table)
+        .values((hstore_table::id.eq(2), hstore_table::store.eq(&m)))
+        .execute(&db)
+        .expect("To insert data");
+
+    let data: HasHstore = hstore_table::table
+        .find(2)
+        .get_result(&db)
+        .expect("To get data");
+    assert_eq!(data.store[""], "blank");
+}
+
+#[test]
+fn strict_empty_key_rejected() {
+    let db = test_connection();
+    make_table(&db);
+
+    let mut m = StrictHstore::new().reject_empty_keys(true);
+    m.insert("  ", "blank").unwrap();
+
+    let result = diesel::insert_into(hstore_table::table)
+        .values((hstore_table::id.eq(2), hstore_table::store.eq(&m)))
+        .execute(&db);
+    assert!(result.is_err());
+
+    m.remove("");
+    m.insert("key", "value").unwrap();
+    diesel::insert_into(hstore_table::table)
+        .values((hstore_table::id.eq(2), hstore_table::store.eq(&m)))
+        .execute(&db)
+        .expect("To insert data");
+}
+
+#[test]
+fn flat_array_round_trip() {
+    use diesel::dsl::sql;
+    use diesel::types::{Array, Text};
+    use diesel_pg_hstore::functions::hstore_from_flat_array;
+
+    let db = test_connection();
+    make_table(&db);
+
+    let flat: Vec<String> = hstore_table::table
+        .select(sql::<Array<Text>>("%% store"))
+        .filter(hstore_table::id.eq(1))
+        .get_result(&db)
+        .expect("To get data");
+    let m = Hstore::from_flat_strings(&flat).expect("An even number of strings");
+    assert_eq!(m.len(), 2);
+    assert_eq!(m["a"], "1");
+    assert_eq!(m["b"], "2");
+
+    let mut m = Hstore::new();
+    m.insert("x".into(), "10".into());
+    m.insert("y".into(), "".into());
+
+    let stored: Hstore = diesel::select(hstore_from_flat_array(m.to_flat_strings()))
+        .get_result(&db)
+        .expect("To get data");
+    assert_eq!(stored, m);
+}
+
+#[test]
+fn special_characters_round_trip() {
+    let db = test_connection();
+    make_table(&db);
+
+    let mut m = Hstore::new();
+    m.insert("a'b".into(), "it's".into());
+    m.insert("c=>d".into(), "e=>f".into());
+    m.insert("e,f".into(), "g, h".into());
+    m.insert("g\"h".into(), "\"quoted\"".into());
+    m.insert("back\\slash".into(), "\\".into());
+    m.insert("NULL".into(), "NULL".into());
+    m.insert("".into(), "'); DROP TABLE hstore_table; --".into());
+
+    diesel::insert_into(hstore_table::table)
+        .values(&HasHstore { id: 2, store: m.clone() })
+        .execute(&db)
+        .expect("To insert data");
+
+    let data: HasHstore = hstore_table::table
+        .find(2)
+        .get_result(&db)
+        .expect("To get data");
+    assert_eq!(data.store, m);
+
+    let ids: Vec<i32> = hstore_table::table
+        .select(hstore_table::id)
+        .filter(hstore_table::store.eq(&m))
+        .load(&db)
+        .expect("To get data");
+    assert_eq!(ids, vec![2]);
+}
+
+#[test]
+fn empty_hstore_in_query() {
+    use diesel::dsl::sql;
+
+    let db = test_connection();
+    make_table(&db);
+
+    let empty = Hstore::new();
+    diesel::insert_into(hstore_table::table)
+        .values(&HasHstore { id: 2, store: empty.clone() })
+        .execute(&db)
+        .expect("To insert data");
+
+    let ids: Vec<i32> = hstore_table::table
+        .select(hstore_table::id)
+        .filter(hstore_table::store.eq(&empty))
+        .filter(hstore_table::store.eq(sql::<Hstore>("''::hstore")))
+        .load(&db)
+        .expect("To get data");
+    assert_eq!(ids, vec![2]);
+}
+
+#[test]
+fn control_characters_in_update() {
+    let db = test_connection();
+    make_table(&db);
+
+    diesel::insert_into(hstore_table::table)
+        .values(&HasHstore { id: 2, store: Hstore::new() })
+        .execute(&db)
+        .expect("To insert data");
+
+    let mut m = Hstore::new();
+    m.insert("line\nbreak".into(), "tab\there".into());
+    m.insert("bell\u{7}".into(), "\u{1b}[0m".into());
+    m.insert("snow\u{2603}".into(), "\u{1f600}".into());
+
+    diesel::update(hstore_table::table.find(2))
+        .set(hstore_table::store.eq(&m))
+        .execute(&db)
+        .expect("To update data");
+
+    let data: HasHstore = hstore_table::table
+        .find(2)
+        .get_result(&db)
+        .expect("To get data");
+    assert_eq!(data.store, m);
+}
+
+table! {
+    use diesel::types::*;
+    use diesel_pg_hstore::HstoreNullable;
+
+    nullable_hstore_table {
+        id -> Integer,
+        store -> HstoreNullable,
+    }
+}
+
+#[derive(Insertable, Queryable, Debug, PartialEq)]
+#[table_name = "nullable_hstore_table"]
+struct HasHstoreNullable {
+    id: i32,
+    store: HstoreNullable,
+}
+
+#[test]
+fn nullable_round_trip() {
+    let db = test_connection();
+    db.batch_execute(r#"
+        CREATE EXTENSION IF NOT EXISTS hstore;
+        DROP TABLE IF EXISTS nullable_hstore_table;
+        CREATE TABLE nullable_hstore_table (
+            id SERIAL PRIMARY KEY,
+            store hstore NOT NULL
+        );
+        INSERT INTO nullable_hstore_table (id, store)
+          VALUES (1, 'a=>1,b=>NULL'::hstore);
+    "#).unwrap();
+
+    let data: HasHstoreNullable = nullable_hstore_table::table
+        .find(1)
+        .get_result(&db)
+        .expect("To get data");
+    assert_eq!(data.store.len(), 2);
+    assert_eq!(data.store["a"], Some("1".into()));
+    assert_eq!(data.store["b"], None);
+
+    let mut m = HstoreNullable::new();
+    m.insert("present".into(), Some("value".into()));
+    m.insert("absent".into(), None);
+    m.insert("NULL".into(), Some("NULL".into()));
+    let row = HasHstoreNullable { id: 2, store: m };
+
+    diesel::insert_into(nullable_hstore_table::table)
+        .values(&row)
+        .execute(&db)
+        .expect("To insert data");
+
+    let data: HasHstoreNullable = nullable_hstore_table::table
+        .find(2)
+        .get_result(&db)
+        .expect("To get data");
+    assert_eq!(data, row);
+
+    let ids: Vec<i32> = nullable_hstore_table::table
+        .select(nullable_hstore_table::id)
+        .filter(nullable_hstore_table::store.eq(&row.store))
+        .load(&db)
+        .expect("To get data");
+    assert_eq!(ids, vec![2]);
+}
+
+#[test]
+fn to_flat_array() {
+    let db = test_connection();
+    make_table(&db);
+
+    let flat: Vec<Option<String>> = hstore_table::table
+        .select(hstore_table::store.to_flat_array())
+        .filter(hstore_table::id.eq(1))
+        .get_result(&db)
+        .expect("To get data");
+    assert_eq!(flat, vec![Some("a".into()), Some("1".into()), Some("b".into()), Some("2".into())]);
+
+    db.batch_execute("INSERT INTO hstore_table (id, store) VALUES (2, 'k=>NULL'::hstore);").unwrap();
+
+    let flat: Vec<Option<String>> = hstore_table::table
+        .select(hstore_table::store.to_flat_array())
+        .filter(hstore_table::id.eq(2))
+        .get_result(&db)
+        .expect("To get data");
+    assert_eq!(flat, vec![Some("k".into()), None]);
+}
+
+#[test]
+fn to_matrix() {
+    let db = test_connection();
+    make_table(&db);
+    db.batch_execute(r#"
+        INSERT INTO hstore_table (id, store) VALUES (2, 'k=>NULL,l=>x'::hstore);
+        INSERT INTO hstore_table (id, store) VALUES (3, ''::hstore);
+    "#).unwrap();
+
+    let mut matrix: Vec<Vec<String>> = hstore_table::table
+        .select(hstore_table::store.to_matrix())
+        .filter(hstore_table::id.eq(1))
+        .get_result(&db)
+        .expect("To get data");
+    matrix.sort();
+    assert_eq!(matrix, vec![vec!["a".to_string(), "1".to_string()],
+                            vec!["b".to_string(), "2".to_string()]]);
+
+    let mut matrix: Vec<Vec<Option<String>>> = hstore_table::table
+        .select(hstore_table::store.to_matrix())
+        .filter(hstore_table::id.eq(2))
+        .get_result(&db)
+        .expect("To get data");
+    matrix.sort();
+    assert_eq!(matrix, vec![vec![Some("k".into()), None], vec![Some("l".into()), Some("x".into())]]);
+
+    let matrix: Vec<Vec<String>> = hstore_table::table
+        .select(hstore_table::store.to_matrix())
+        .filter(hstore_table::id.eq(2))
+        .get_result(&db)
+        .expect("To get data");
+    assert_eq!(matrix, vec![vec!["l".to_string(), "x".to_string()]]);
+
+    let matrix: Vec<Vec<String>> = hstore_table::table
+        .select(hstore_table::store.to_matrix())
+        .filter(hstore_table::id.eq(3))
+        .get_result(&db)
+        .expect("To get data");
+    assert!(matrix.is_empty());
+}
+
+#[test]
+fn skeys_and_svals() {
+    use diesel_pg_hstore::functions::{hstore_skeys, hstore_svals};
+
+    let db = test_connection();
+    make_table(&db);
+    db.batch_execute("INSERT INTO hstore_table (id, store) VALUES (2, 'c=>3,d=>NULL'::hstore);").unwrap();
+
+    let mut keys: Vec<String> = hstore_table::table
+        .select(hstore_skeys(hstore_table::store))
+        .load(&db)
+        .expect("To get data");
+    keys.sort();
+    assert_eq!(keys, vec!["a", "b", "c", "d"]);
+
+    let mut values: Vec<Option<String>> = hstore_table::table
+        .select(hstore_svals(hstore_table::store))
+        .filter(hstore_table::id.eq(2))
+        .load(&db)
+        .expect("To get data");
+    values.sort();
+    assert_eq!(values, vec![None, Some("3".into())]);
+}
+
+#[test]
+fn non_empty_rejects_empty_values() {
+    let db = test_connection();
+    make_table(&db);
+    db.batch_execute(r#"INSERT INTO hstore_table (id, store) VALUES (2, 'a=>1,b=>""'::hstore);"#).unwrap();
+
+    let stored: NonEmptyHstore = hstore_table::table
+        .select(hstore_table::store)
+        .filter(hstore_table::id.eq(1))
+        .get_result(&db)
+        .expect("To get data");
+    assert_eq!(stored["a"], "1");
+
+    let mut m = NonEmptyHstore::new();
+    m.insert("c", "3").unwrap();
+    diesel::update(hstore_table::table.find(1))
+        .set(hstore_table::store.eq(&m))
+        .execute(&db)
+        .expect("To update data");
+    let data: HasHstore = hstore_table::table.find(1).get_result(&db).expect("To get data");
+    assert_eq!(data.store, *m);
+
+    let result = hstore_table::table
+        .select(hstore_table::store)
+        .filter(hstore_table::id.eq(2))
+        .get_result::<NonEmptyHstore>(&db);
+    assert!(result.is_err());
+}
+
+#[test]
+fn key_count() {
+    let db = test_connection();
+    make_table(&db);
+    db.batch_execute(r#"
+        INSERT INTO hstore_table (id, store) VALUES (2, ''::hstore);
+        INSERT INTO hstore_table (id, store) VALUES (3, 'x=>1'::hstore);
+        INSERT INTO hstore_table (id, store) VALUES (4, 'x=>1,y=>2,z=>NULL'::hstore);
+    "#).unwrap();
+
+    let counts: Vec<(i32, Option<i32>)> = hstore_table::table
+        .select((hstore_table::id, hstore_table::store.key_count()))
+        .filter(hstore_table::id.gt(1))
+        .order(hstore_table::id)
+        .load(&db)
+        .expect("To get data");
+    assert_eq!(counts, vec![(2, None), (3, Some(1)), (4, Some(3))]);
+}
+
+#[test]
+fn each() {
+    use diesel_pg_hstore::functions::hstore_each;
+
+    let db = test_connection();
+    make_table(&db);
+
+    let mut entries: Vec<(String, Option<String>)> = hstore_table::table
+        .select(hstore_each(hstore_table::store))
+        .filter(hstore_table::id.eq(1))
+        .load(&db)
+        .expect("To get data");
+    entries.sort();
+    assert_eq!(entries, vec![("a".into(), Some("1".into())), ("b".into(), Some("2".into()))]);
+
+    db.batch_execute("INSERT INTO hstore_table (id, store) VALUES (2, 'c=>NULL'::hstore);").unwrap();
+
+    let entries: Vec<(i32, (String, Option<String>))> = hstore_table::table
+        .select((hstore_table::id, hstore_each(hstore_table::store)))
+        .filter(hstore_table::id.eq(2))
+        .load(&db)
+        .expect("To get data");
+    assert_eq!(entries, vec![(2, ("c".into(), None))]);
+}
+
+#[test]
+fn has_key_and_defined() {
+    let db = test_connection();
+    make_table(&db);
+    db.batch_execute("INSERT INTO hstore_table (id, store) VALUES (2, 'a=>NULL'::hstore);").unwrap();
+
+    let with_key: Vec<i32> = hstore_table::table
+        .select(hstore_table::id)
+        .filter(hstore_table::store.has_key("a"))
+        .order(hstore_table::id)
+        .load(&db)
+        .expect("To get data");
+    assert_eq!(with_key, vec![1, 2]);
+
+    let defined: Vec<i32> = hstore_table::table
+        .select(hstore_table::id)
+        .filter(hstore_table::store.defined("a"))
+        .load(&db)
+        .expect("To get data");
+    assert_eq!(defined, vec![1]);
+
+    let missing: Vec<i32> = hstore_table::table
+        .select(hstore_table::id)
+        .filter(hstore_table::store.has_key("z").or(hstore_table::store.defined("z")))
+        .load(&db)
+        .expect("To get data");
+    assert!(missing.is_empty());
+}
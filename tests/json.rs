@@ -0,0 +1,182 @@
+#![cfg(feature = "json")]
+
+#[macro_use]
+extern crate diesel;
+extern crate diesel_pg_hstore;
+extern crate dotenv;
+#[macro_use]
+extern crate serde_json;
+
+use std::convert::TryFrom;
+use std::env;
+
+use diesel::prelude::*;
+use diesel::Connection;
+use diesel::pg::PgConnection;
+use diesel::connection::SimpleConnection;
+
+use diesel_pg_hstore::Hstore;
+use diesel_pg_hstore::json::{JsonConversionError, MergePatchError};
+
+fn connection() -> PgConnection {
+    dotenv::dotenv().ok();
+    let database_url = env::var("DATABASE_URL").expect("DATABASE_URL to be defined (may use .env)");
+    let db = PgConnection::establish(&database_url).unwrap();
+    db.begin_test_transaction().unwrap();
+    db
+}
+
+table! {
+    jsonb_table {
+        id -> Integer,
+        data -> Jsonb,
+    }
+}
+
+fn make_table(db: &PgConnection) {
+    db.batch_execute(r#"
+        DROP TABLE IF EXISTS jsonb_table;
+        CREATE TABLE jsonb_table (
+            id SERIAL PRIMARY KEY,
+            data jsonb NOT NULL
+        );
+        INSERT INTO jsonb_table (id, data)
+          VALUES (1, '{"a": 1, "b": "x"}'::jsonb);
+    "#).unwrap();
+}
+
+#[test]
+fn merge_into_jsonb() {
+    let db = connection();
+    make_table(&db);
+
+    let mut m = Hstore::new();
+    m.insert("b".into(), "y".into());
+    m.insert("c".into(), "2".into());
+
+    diesel::update(jsonb_table::table.filter(jsonb_table::id.eq(1)))
+        .set(jsonb_table::data.eq(m.merge_into_jsonb(jsonb_table::data)))
+        .execute(&db)
+        .expect("To update data");
+
+    let data: serde_json::Value = jsonb_table::table
+        .select(jsonb_table::data)
+        .get_result(&db)
+        .expect("To get data");
+
+    assert_eq!(data, json!({"a": 1, "b": "y", "c": "2"}));
+}
+
+#[test]
+fn hstore_to_json_functions() {
+    use diesel::dsl::sql;
+    use diesel_pg_hstore::functions::{hstore_to_json, hstore_to_json_loose, hstore_to_jsonb,
+                                      hstore_to_jsonb_loose};
+
+    let db = connection();
+    let stored = || sql::<Hstore>("'a=>1, b=>t, c=>x, d=>NULL'::hstore");
+
+    let json: serde_json::Value = diesel::select(hstore_to_json(stored()))
+        .get_result(&db)
+        .expect("To get data");
+    assert_eq!(json, json!({"a": "1", "b": "t", "c": "x", "d": null}));
+
+    let json: serde_json::Value = diesel::select(hstore_to_json_loose(stored()))
+        .get_result(&db)
+        .expect("To get data");
+    assert_eq!(json, json!({"a": 1, "b": true, "c": "x", "d": null}));
+
+    let mut m = Hstore::new();
+    m.insert("k".into(), "v".into());
+    let jsonb: serde_json::Value = diesel::select(hstore_to_jsonb(&m))
+        .get_result(&db)
+        .expect("To get data");
+    assert_eq!(jsonb, m.to_json_value());
+
+    let jsonb: serde_json::Value = diesel::select(hstore_to_jsonb_loose(stored()))
+        .get_result(&db)
+        .expect("To get data");
+    assert_eq!(jsonb, json!({"a": 1, "b": true, "c": "x", "d": null}));
+}
+
+#[test]
+fn to_json_value() {
+    let mut m = Hstore::new();
+    m.insert("count".into(), "5".into());
+    m.insert("on".into(), "true".into());
+    m.insert("name".into(), "widget".into());
+
+    assert_eq!(m.to_json_value(), json!({"count": "5", "on": "true", "name": "widget"}));
+}
+
+#[test]
+fn from_json_value() {
+    let m = Hstore::from_json_value(&json!({"a": "1", "b": "", "c": null})).unwrap();
+
+    assert_eq!(m.len(), 2);
+    assert_eq!(m["a"], "1");
+    assert_eq!(m["b"], "");
+    assert_eq!(serde_json::Value::from(&m), json!({"a": "1", "b": ""}));
+}
+
+#[test]
+fn try_from_json_value() {
+    let value = json!({"a": "1", "c": null});
+    let m = Hstore::try_from(&value).unwrap();
+    assert_eq!(m, Hstore::from_json_value(&value).unwrap());
+    assert_eq!(m.len(), 1);
+
+    assert_eq!(Hstore::try_from(&json!("a")), Err(JsonConversionError::NotAnObject));
+}
+
+#[test]
+fn from_json_value_rejects_non_strings() {
+    assert_eq!(Hstore::from_json_value(&json!(["a", "1"])), Err(JsonConversionError::NotAnObject));
+    assert_eq!(Hstore::from_json_value(&json!({"a": "1", "tags": ["x"]})),
+               Err(JsonConversionError::NonStringValue("tags".into())));
+    assert_eq!(Hstore::from_json_value(&json!({"count": 5})),
+               Err(JsonConversionError::NonStringValue("count".into())));
+    assert_eq!(Hstore::from_json_value(&json!({"nested": {"a": "1"}})),
+               Err(JsonConversionError::NonStringValue("nested".into())));
+}
+
+fn patch_target() -> Hstore {
+    let mut m = Hstore::new();
+    m.insert("a".into(), "1".into());
+    m.insert("b".into(), "2".into());
+    m
+}
+
+#[test]
+fn merge_patch_sets_and_overwrites() {
+    let mut m = patch_target();
+    m.apply_merge_patch(&json!({"b": "two", "c": 3, "d": true, "e": 1.5})).unwrap();
+
+    assert_eq!(m.len(), 5);
+    assert_eq!(m["a"], "1");
+    assert_eq!(m["b"], "two");
+    assert_eq!(m["c"], "3");
+    assert_eq!(m["d"], "true");
+    assert_eq!(m["e"], "1.5");
+}
+
+#[test]
+fn merge_patch_null_removes() {
+    let mut m = patch_target();
+    m.apply_merge_patch(&json!({"a": null, "missing": null})).unwrap();
+
+    assert_eq!(m.len(), 1);
+    assert_eq!(m["b"], "2");
+}
+
+#[test]
+fn merge_patch_rejects_nested_values() {
+    let mut m = patch_target();
+
+    assert_eq!(m.apply_merge_patch(&json!({"a": null, "b": {"c": 1}})),
+               Err(MergePatchError::NestedValue("b".to_string())));
+    assert_eq!(m.apply_merge_patch(&json!({"b": [1]})),
+               Err(MergePatchError::NestedValue("b".to_string())));
+    assert_eq!(m.apply_merge_patch(&json!("a")), Err(MergePatchError::NotAnObject));
+    assert_eq!(m, patch_target());
+}
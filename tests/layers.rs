@@ -0,0 +1,49 @@
+extern crate diesel_pg_hstore;
+
+use diesel_pg_hstore::{Hstore, KeyMatcher};
+
+#[test]
+fn prefix_and_any() {
+    assert!(KeyMatcher::Any.matches(""));
+    assert!(KeyMatcher::Prefix("ui.".into()).matches("ui."));
+    assert!(!KeyMatcher::Prefix("ui.".into()).matches("ui"));
+    assert!(KeyMatcher::Prefix("".into()).matches("anything"));
+}
+
+#[test]
+fn glob() {
+    let matches = |pattern: &str, key: &str| KeyMatcher::Glob(pattern.into()).matches(key);
+
+    assert!(matches("*", ""));
+    assert!(matches("*", "anything"));
+    assert!(matches("secret.*", "secret.token"));
+    assert!(matches("*.key", "db.primary.key"));
+    assert!(matches("a*b*c", "aXbYbZc"));
+    assert!(matches("?é?", "aéb"));
+    assert!(!matches("secret.*", "secrets.token"));
+    assert!(!matches("a*b", "aXbY"));
+    assert!(!matches("?", ""));
+    assert!(!matches("", "a"));
+}
+
+#[test]
+fn resolve_layers() {
+    let env = Hstore::from_array([("secret.token", "env"), ("ui.theme", "env"), ("port", "env")]);
+    let file = Hstore::from_array([("secret.token", "file"), ("ui.theme", "file"),
+                                    ("ui.font", "file")]);
+    let defaults = Hstore::from_array([("port", "80"), ("secret.token", "default")]);
+
+    let settings = Hstore::resolve_layers(&[
+        (KeyMatcher::Any, defaults),
+        (KeyMatcher::Glob("secret.*".into()), env),
+        (KeyMatcher::Prefix("ui.".into()), file),
+    ]);
+
+    assert_eq!(settings, Hstore::from_array([
+        ("port", "80"),
+        ("secret.token", "env"),
+        ("ui.theme", "file"),
+        ("ui.font", "file"),
+    ]));
+    assert!(Hstore::resolve_layers(&[]).is_empty());
+}
@@ -0,0 +1,741 @@
+extern crate diesel_pg_hstore;
+
+use std::collections::HashMap;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{BuildHasherDefault, Hash, Hasher};
+use std::sync::Arc;
+
+use diesel_pg_hstore::{AnnotatedHstore, EmptyValueError, Hstore, HstoreEntry, HstoreLenMismatch,
+                       HstoreNullable, KeyOverride, KeyValue, NonEmptyHstore, StrictHstore, needs_quoting};
+
+#[test]
+fn with_empty_as_null() {
+    let mut m = Hstore::new();
+    m.insert("empty".into(), "".into());
+    m.insert("full".into(), "value".into());
+
+    let nullable = m.with_empty_as_null();
+
+    assert_eq!(nullable.len(), 2);
+    assert_eq!(nullable["empty"], None);
+    assert_eq!(nullable["full"], Some("value".to_string()));
+}
+
+#[test]
+fn simple_tokens_need_no_quoting() {
+    assert!(!needs_quoting("a"));
+    assert!(!needs_quoting("key_1"));
+    assert!(!needs_quoting("ünïcode"));
+}
+
+#[test]
+fn complex_tokens_need_quoting() {
+    assert!(needs_quoting(""));
+    assert!(needs_quoting("b c"));
+    assert!(needs_quoting("a=>b"));
+    assert!(needs_quoting("a,b"));
+    assert!(needs_quoting("a\"b"));
+    assert!(needs_quoting("a\\b"));
+    assert!(needs_quoting("null"));
+}
+
+#[test]
+fn content_bytes() {
+    let mut m = Hstore::new();
+    m.insert("ascii".into(), "value".into());
+    m.insert("ключ".into(), "値".into());
+
+    assert_eq!(m.content_bytes(), 5 + 5 + 8 + 3);
+}
+
+#[test]
+fn patch_and_inverse() {
+    let mut old = Hstore::new();
+    old.insert("same".into(), "1".into());
+    old.insert("changed".into(), "2".into());
+    old.insert("removed".into(), "3".into());
+
+    let mut new = Hstore::new();
+    new.insert("same".into(), "1".into());
+    new.insert("changed".into(), "two".into());
+    new.insert("added".into(), "4".into());
+
+    let (patch, inverse) = Hstore::patch_and_inverse(&old, &new);
+
+    assert_eq!(patch.set.len(), 2);
+    assert_eq!(patch.set["changed"], "two");
+    assert_eq!(patch.set["added"], "4");
+    assert_eq!(patch.remove, vec!["removed".to_string()]);
+
+    let mut edited = old.clone();
+    patch.apply(&mut edited);
+    assert_eq!(edited, new);
+
+    inverse.apply(&mut edited);
+    assert_eq!(edited, old);
+}
+
+#[test]
+fn patch_between_equal_hstores_is_empty() {
+    let mut m = Hstore::new();
+    m.insert("a".into(), "1".into());
+
+    let (patch, inverse) = Hstore::patch_and_inverse(&m, &m.clone());
+    assert!(patch.is_empty());
+    assert!(inverse.is_empty());
+}
+
+#[test]
+fn from_hashmap_into() {
+    let mut hm = HashMap::new();
+    hm.insert("a".to_string(), "1".to_string());
+
+    let m: Hstore = hm.clone().into();
+    assert_eq!(m, Hstore::from_hashmap(hm));
+}
+
+#[test]
+fn into_hashmap() {
+    let mut m = Hstore::new();
+    m.insert("a".into(), "1".into());
+
+    let hm: HashMap<String, String> = m.clone().into();
+    assert_eq!(hm.len(), 1);
+    assert_eq!(hm["a"], "1");
+    assert_eq!(Hstore::from(hm), m);
+}
+
+#[test]
+fn from_hashmap_with_hasher() {
+    let mut hm: HashMap<String, String, BuildHasherDefault<DefaultHasher>> = HashMap::default();
+    hm.insert("a".into(), "1".into());
+    hm.insert("b".into(), "2".into());
+
+    let m = Hstore::from_hashmap_with_hasher(hm);
+
+    assert_eq!(m.len(), 2);
+    assert_eq!(m["a"], "1");
+    assert_eq!(m["b"], "2");
+}
+
+#[test]
+fn strict_insert_trims_keys() {
+    let mut m = StrictHstore::new();
+    m.insert(" a ", "1").unwrap();
+    m.insert("b", "2").unwrap();
+
+    assert_eq!(m.len(), 2);
+    assert_eq!(m["a"], "1");
+    assert_eq!(m["b"], "2");
+}
+
+#[test]
+fn strict_insert_rejects_colliding_keys() {
+    let mut m = StrictHstore::new();
+    m.insert("key", "1").unwrap();
+
+    let err = m.insert("key ", "2").unwrap_err();
+    assert_eq!(err.key, "key");
+    assert_eq!(m.len(), 1);
+    assert_eq!(m["key"], "1");
+}
+
+#[test]
+fn strict_insert_overwrites_same_key() {
+    let mut m = StrictHstore::new();
+    m.insert("a", "1").unwrap();
+    m.insert("a", "2").unwrap();
+    m.insert(" b", "1").unwrap();
+    m.insert(" b", "2").unwrap();
+
+    assert_eq!(m.len(), 2);
+    assert_eq!(m["a"], "2");
+    assert_eq!(m["b"], "2");
+
+    assert!(m.insert("b", "3").is_err());
+    assert_eq!(m.remove("b"), Some("2".into()));
+    m.insert("b", "3").unwrap();
+    assert_eq!(m["b"], "3");
+}
+
+#[test]
+fn non_empty_insert_rejects_empty_values() {
+    let mut m = NonEmptyHstore::new();
+    assert_eq!(m.insert("a", "1"), Ok(None));
+    assert_eq!(m.insert("a", "2"), Ok(Some("1".to_string())));
+
+    assert_eq!(m.insert("b", ""), Err(EmptyValueError { key: "b".into() }));
+    assert_eq!(m.insert("a", ""), Err(EmptyValueError { key: "a".into() }));
+    assert_eq!(m.len(), 1);
+    assert_eq!(m["a"], "2");
+}
+
+#[test]
+fn non_empty_from_hstore() {
+    let mut h = Hstore::new();
+    h.insert("a".into(), "1".into());
+    assert_eq!(NonEmptyHstore::from_hstore(h.clone()).unwrap().into_inner(), h);
+
+    h.insert("c".into(), "".into());
+    h.insert("b".into(), "".into());
+    assert_eq!(NonEmptyHstore::from_hstore(h), Err(EmptyValueError { key: "b".into() }));
+}
+
+#[test]
+fn group_by_key() {
+    let mut m = Hstore::new();
+    m.insert("ui.x".into(), "1".into());
+    m.insert("ui.y".into(), "2".into());
+    m.insert("db.y".into(), "3".into());
+    m.insert("plain".into(), "4".into());
+
+    let groups = m.group_by_key(|k| k.split('.').next().unwrap().to_string());
+
+    assert_eq!(groups.len(), 3);
+    assert_eq!(groups["ui"].len(), 2);
+    assert_eq!(groups["ui"]["ui.x"], "1");
+    assert_eq!(groups["ui"]["ui.y"], "2");
+    assert_eq!(groups["db"].len(), 1);
+    assert_eq!(groups["db"]["db.y"], "3");
+    assert_eq!(groups["plain"]["plain"], "4");
+}
+
+#[test]
+fn split_namespaces() {
+    let mut m = Hstore::new();
+    m.insert("ui.x".into(), "1".into());
+    m.insert("ui.y".into(), "2".into());
+    m.insert("db.pool.size".into(), "3".into());
+    m.insert("plain".into(), "4".into());
+    m.insert(".leading".into(), "5".into());
+
+    let namespaces = m.split_namespaces('.');
+
+    assert_eq!(namespaces.len(), 4);
+    assert_eq!(namespaces["ui"].len(), 2);
+    assert_eq!(namespaces["ui"]["x"], "1");
+    assert_eq!(namespaces["ui"]["y"], "2");
+    assert_eq!(namespaces["db"].len(), 1);
+    assert_eq!(namespaces["db"]["pool.size"], "3");
+    assert_eq!(namespaces["."].len(), 1);
+    assert_eq!(namespaces["."]["plain"], "4");
+    assert_eq!(namespaces[""].len(), 1);
+    assert_eq!(namespaces[""]["leading"], "5");
+}
+
+#[test]
+fn split_namespaces_leading_separator() {
+    let mut m = Hstore::new();
+    m.insert("a".into(), "bare".into());
+    m.insert(".a".into(), "leading".into());
+
+    let namespaces = m.split_namespaces('.');
+
+    assert_eq!(namespaces.len(), 2);
+    assert_eq!(namespaces["."]["a"], "bare");
+    assert_eq!(namespaces[""]["a"], "leading");
+}
+
+#[test]
+fn hstore_into_nullable() {
+    let mut m = Hstore::new();
+    m.insert("a".into(), "1".into());
+    m.insert("b".into(), "".into());
+
+    let nullable = HstoreNullable::from(m);
+
+    assert_eq!(nullable.len(), 2);
+    assert_eq!(nullable["a"], Some("1".to_string()));
+    assert_eq!(nullable["b"], Some("".to_string()));
+}
+
+#[test]
+fn nullable_into_lossy() {
+    let mut nullable = HstoreNullable::new();
+    nullable.insert("a".into(), Some("1".into()));
+    nullable.insert("b".into(), None);
+
+    let m = nullable.into_lossy();
+
+    assert_eq!(m.len(), 1);
+    assert_eq!(m["a"], "1");
+    assert!(!m.contains_key("b"));
+}
+
+#[test]
+fn wire_size() {
+    let mut m = Hstore::new();
+    assert_eq!(m.wire_size(), 4);
+
+    m.insert("ab".into(), "cde".into());
+    assert_eq!(m.wire_size(), 4 + 4 + 2 + 4 + 3);
+    assert_eq!(m.wire_size(), m.to_wire_bytes().unwrap().len());
+}
+
+#[test]
+fn likely_toasted() {
+    let mut m = Hstore::new();
+    m.insert("a".into(), "1".into());
+    assert!(!m.likely_toasted());
+
+    // 4 bytes of entry count, 8 bytes of length prefixes and a one byte key
+    m.insert("a".into(), "x".repeat(2032 - 13));
+    assert!(!m.likely_toasted());
+
+    m.insert("a".into(), "x".repeat(2032 - 12));
+    assert!(m.likely_toasted());
+}
+
+#[test]
+fn merge() {
+    let mut m = Hstore::from_array([("a", "1"), ("b", "2")]);
+    m.merge(Hstore::from_array([("b", "20"), ("c", "30")]));
+    assert_eq!(m, Hstore::from_array([("a", "1"), ("b", "20"), ("c", "30")]));
+
+    let merged = Hstore::from_array([("a", "1")]).merged(Hstore::new());
+    assert_eq!(merged, Hstore::from_array([("a", "1")]));
+}
+
+#[test]
+fn hash_ignores_insertion_order() {
+    fn hash(m: &Hstore) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        m.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    let mut forward = Hstore::new();
+    let mut backward = Hstore::new();
+    for i in 0..100 {
+        forward.insert(format!("key{}", i), i.to_string());
+        backward.insert(format!("key{}", 99 - i), (99 - i).to_string());
+    }
+
+    assert_eq!(forward, backward);
+    assert_eq!(hash(&forward), hash(&backward));
+
+    backward.insert("key0".into(), "changed".into());
+    assert_ne!(hash(&forward), hash(&backward));
+    assert_ne!(hash(&Hstore::from_array([("a", "b")])), hash(&Hstore::from_array([("b", "a")])));
+}
+
+#[test]
+fn merge_with() {
+    let mut m = Hstore::new();
+    m.insert("a".into(), "1".into());
+    m.insert("b".into(), "2".into());
+
+    let mut other = Hstore::new();
+    other.insert("a".into(), "10".into());
+    other.insert("b".into(), "20".into());
+    other.insert("c".into(), "30".into());
+
+    let mut seen = Vec::new();
+    m.merge_with(other, |k, old, new| {
+        seen.push((k.to_string(), old.to_string(), new.to_string()));
+        k == "b"
+    });
+    seen.sort();
+
+    assert_eq!(m.len(), 3);
+    assert_eq!(m["a"], "1");
+    assert_eq!(m["b"], "20");
+    assert_eq!(m["c"], "30");
+    assert_eq!(seen, vec![
+        ("a".to_string(), "1".to_string(), "10".to_string()),
+        ("b".to_string(), "2".to_string(), "20".to_string()),
+    ]);
+}
+
+#[test]
+fn into_arc() {
+    let mut m = Hstore::new();
+    m.insert("a".into(), "1".into());
+    let value = m["a"].as_ptr();
+
+    let shared = m.into_arc();
+    let handler = Arc::clone(&shared);
+    assert!(Arc::ptr_eq(&shared, &handler));
+    assert_eq!(shared["a"].as_ptr(), value);
+    assert_eq!(handler["a"].as_ptr(), value);
+    assert_eq!(Arc::strong_count(&shared), 2);
+}
+
+#[test]
+fn from_display_fields() {
+    let name = String::from("widget");
+    let m = Hstore::from_display_fields(&[("count", &5), ("on", &true), ("name", &name), ("ratio", &0.5)]);
+
+    assert_eq!(m.len(), 4);
+    assert_eq!(m["count"], "5");
+    assert_eq!(m["on"], "true");
+    assert_eq!(m["name"], "widget");
+    assert_eq!(m["ratio"], "0.5");
+}
+
+#[test]
+fn missing_keys() {
+    let mut m = Hstore::new();
+    m.insert("a".into(), "1".into());
+    m.insert("c".into(), "".into());
+
+    assert_eq!(m.missing_keys(&["a", "b", "c", "d"]), vec!["b", "d"]);
+    assert!(m.missing_keys(&["c", "a"]).is_empty());
+    assert!(m.missing_keys(&[]).is_empty());
+}
+
+#[test]
+fn unparseable_values() {
+    let m = Hstore::from_array([("a", "1"), ("b", "-20"), ("c", "1.5"), ("d", ""), ("e", "x")]);
+
+    let bad: Vec<(&str, &str)> = m.unparseable_values::<i64>()
+        .into_iter()
+        .map(|(k, v)| (k.as_str(), v.as_str()))
+        .collect();
+    assert_eq!(bad, vec![("c", "1.5"), ("d", ""), ("e", "x")]);
+    assert!(Hstore::new().unparseable_values::<i64>().is_empty());
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Layer {
+    Defaults,
+    Site,
+    User,
+}
+
+#[test]
+fn annotated_merge() {
+    let mut defaults = Hstore::new();
+    defaults.insert("a".into(), "1".into());
+    defaults.insert("b".into(), "1".into());
+    defaults.insert("c".into(), "1".into());
+    let mut site = Hstore::new();
+    site.insert("b".into(), "2".into());
+    site.insert("c".into(), "2".into());
+    let mut user = Hstore::new();
+    user.insert("c".into(), "3".into());
+
+    let mut m = AnnotatedHstore::from_hstore(defaults, Layer::Defaults);
+    m.merge(AnnotatedHstore::from_hstore(site, Layer::Site));
+    m.merge(AnnotatedHstore::from_hstore(user, Layer::User));
+
+    assert_eq!(m.len(), 3);
+    assert_eq!(m.metadata("a"), Some(&Layer::Defaults));
+    assert_eq!(m.metadata("b"), Some(&Layer::Site));
+    assert_eq!(m.metadata("c"), Some(&Layer::User));
+    assert_eq!(m.get("c"), Some("3"));
+
+    let plain = m.into_hstore();
+    assert_eq!(plain.len(), 3);
+    assert_eq!(plain["a"], "1");
+    assert_eq!(plain["b"], "2");
+    assert_eq!(plain["c"], "3");
+}
+
+fn whitespace_keys() -> Hstore {
+    let mut m = Hstore::new();
+    m.insert("a".into(), "trimmed".into());
+    m.insert(" a".into(), "leading".into());
+    m.insert("a ".into(), "trailing".into());
+    m.insert(" b ".into(), "both".into());
+    m.insert("b\t".into(), "tab".into());
+    m.insert(" c".into(), "alone".into());
+    m
+}
+
+#[test]
+fn whitespace_key_issues() {
+    let issues = whitespace_keys().whitespace_key_issues();
+
+    assert_eq!(issues, vec![
+        (" a".to_string(), "a".to_string()),
+        (" a".to_string(), "a ".to_string()),
+        (" b ".to_string(), "b\t".to_string()),
+        ("a".to_string(), "a ".to_string()),
+    ]);
+    assert!(Hstore::new().whitespace_key_issues().is_empty());
+}
+
+#[test]
+fn collapse_whitespace_keys() {
+    let mut m = whitespace_keys();
+    m.collapse_whitespace_keys(true);
+
+    assert_eq!(m.len(), 3);
+    assert_eq!(m["a"], "trimmed");
+    assert_eq!(m["b"], "both");
+    assert_eq!(m[" c"], "alone");
+    assert!(m.whitespace_key_issues().is_empty());
+
+    let mut m = whitespace_keys();
+    m.collapse_whitespace_keys(false);
+
+    assert_eq!(m.len(), 3);
+    assert_eq!(m["a"], "leading");
+    assert_eq!(m["b"], "both");
+}
+
+#[test]
+fn entries_by_key() {
+    let mut m = Hstore::new();
+    for k in &["d", "b", "a", "c"] {
+        m.insert(k.to_string(), k.to_uppercase());
+    }
+
+    let entries: Vec<(&str, &str)> = m.entries_by_key()
+        .into_iter()
+        .map(|(k, v)| (k.as_str(), v.as_str()))
+        .collect();
+    assert_eq!(entries, vec![("a", "A"), ("b", "B"), ("c", "C"), ("d", "D")]);
+}
+
+#[test]
+fn into_sorted_vec() {
+    let m: Hstore = (0..50).map(|i| (format!("{:02}", 49 - i), i.to_string())).collect();
+
+    let borrowed: Vec<(String, String)> = m.entries_by_key()
+        .into_iter()
+        .map(|(k, v)| (k.clone(), v.clone()))
+        .collect();
+    let owned = m.into_sorted_vec();
+    assert_eq!(owned, borrowed);
+    assert_eq!(owned[0], ("00".to_string(), "49".to_string()));
+}
+
+#[test]
+fn entries_by_value() {
+    let mut m = Hstore::new();
+    m.insert("d".into(), "2".into());
+    m.insert("b".into(), "1".into());
+    m.insert("c".into(), "2".into());
+    m.insert("a".into(), "2".into());
+
+    let entries: Vec<(&str, &str)> = m.entries_by_value()
+        .into_iter()
+        .map(|(k, v)| (k.as_str(), v.as_str()))
+        .collect();
+
+    assert_eq!(entries, vec![("b", "1"), ("a", "2"), ("c", "2"), ("d", "2")]);
+}
+
+#[test]
+fn try_from_iter() {
+    let entries: Vec<Result<(String, String), &str>> = vec![
+        Ok(("a".into(), "1".into())),
+        Ok(("b".into(), "2".into())),
+    ];
+    let m = Hstore::try_from_iter(entries).unwrap();
+    assert_eq!(m.len(), 2);
+    assert_eq!(m["a"], "1");
+    assert_eq!(m["b"], "2");
+
+    let mut consumed = 0;
+    let entries = vec![
+        Ok(("a".to_string(), "1".to_string())),
+        Ok(("b".to_string(), "2".to_string())),
+        Err("third"),
+        Err("fourth"),
+        Ok(("e".to_string(), "5".to_string())),
+    ];
+    let result = Hstore::try_from_iter(entries.into_iter().inspect(|_| consumed += 1));
+    assert_eq!(result, Err("third"));
+    assert_eq!(consumed, 3);
+}
+
+#[test]
+fn merge_logging() {
+    let mut m = Hstore::new();
+    m.insert("a".into(), "1".into());
+    m.insert("b".into(), "2".into());
+    m.insert("c".into(), "3".into());
+
+    let mut other = Hstore::new();
+    other.insert("c".into(), "30".into());
+    other.insert("a".into(), "1".into());
+    other.insert("d".into(), "40".into());
+
+    let overrides = m.merge_logging(other);
+
+    assert_eq!(overrides, vec![
+        KeyOverride { key: "a".into(), old: "1".into(), new: "1".into() },
+        KeyOverride { key: "c".into(), old: "3".into(), new: "30".into() },
+    ]);
+    assert_eq!(m.len(), 4);
+    assert_eq!(m["c"], "30");
+    assert_eq!(m["d"], "40");
+}
+
+#[test]
+fn flat_strings() {
+    let mut m = Hstore::new();
+    m.insert("b".into(), "2".into());
+    m.insert("a".into(), "1".into());
+
+    let flat = m.to_flat_strings();
+    assert_eq!(flat, vec!["a", "1", "b", "2"]);
+    assert_eq!(Hstore::from_flat_strings(&flat), Ok(m));
+
+    let odd = vec!["a".to_string(), "1".to_string(), "b".to_string()];
+    assert_eq!(Hstore::from_flat_strings(&odd), Err(HstoreLenMismatch { len: 3 }));
+    assert_eq!(Hstore::from_flat_strings(&[]), Ok(Hstore::new()));
+}
+
+#[test]
+fn trim_values() {
+    let mut m = Hstore::new();
+    m.insert("a".into(), "  1  ".into());
+    m.insert(" b".into(), "\t2\n".into());
+    m.insert("c".into(), "3".into());
+    m.insert("d".into(), "   ".into());
+
+    let trimmed = m.trimmed_values();
+    m.trim_values();
+
+    assert_eq!(m, trimmed);
+    assert_eq!(m.len(), 4);
+    assert_eq!(m["a"], "1");
+    assert_eq!(m[" b"], "2");
+    assert_eq!(m["c"], "3");
+    assert_eq!(m["d"], "");
+}
+
+#[test]
+fn get_multi() {
+    let mut m = Hstore::new();
+    m.insert("tags".into(), "red|green|blue".into());
+    m.insert("single".into(), "one".into());
+    m.insert("empty".into(), "".into());
+
+    assert_eq!(m.get_multi("tags", '|'), vec!["red", "green", "blue"]);
+    assert_eq!(m.get_multi("single", '|'), vec!["one"]);
+    assert_eq!(m.get_multi("empty", '|'), Vec::<&str>::new());
+    assert_eq!(m.get_multi("missing", '|'), Vec::<&str>::new());
+}
+
+#[test]
+fn insert_multi() {
+    let mut m = Hstore::new();
+    m.insert("tags".into(), "old".into());
+
+    m.insert_multi("tags", &["red", "green"], '|');
+    m.insert_multi("none", &[], '|');
+
+    assert_eq!(m["tags"], "red|green");
+    assert_eq!(m["none"], "");
+    assert_eq!(m.get_multi("tags", '|'), vec!["red", "green"]);
+
+    m.insert_multi("ambiguous", &["a|b", "c"], '|');
+    assert_eq!(m.get_multi("ambiguous", '|'), vec!["a", "b", "c"]);
+}
+
+#[test]
+fn from_array() {
+    let pairs = [("a", "1"), ("b", "2"), ("c", "")];
+    let m = Hstore::from_array(pairs);
+
+    // One entry for each of the N pairs
+    assert_eq!(m.len(), 3);
+    assert_eq!(m["a"], "1");
+    assert_eq!(m["c"], "");
+
+    let repeated = Hstore::from_array([("a", "1"), ("a", "2")]);
+    assert_eq!(repeated.len(), 1);
+    assert_eq!(repeated["a"], "2");
+    assert!(Hstore::from_array([]).is_empty());
+}
+
+#[test]
+fn entry_str() {
+    let mut m = Hstore::new();
+    m.insert("a".into(), "1".into());
+
+    // The value outlives the key it was looked up with
+    let value = {
+        let key = String::from("a");
+        m.entry_str(&key).or_insert("unused".into())
+    };
+    value.push('!');
+    assert_eq!(m["a"], "1!");
+
+    match m.entry_str("a") {
+        HstoreEntry::Occupied(mut e) => {
+            assert_eq!(e.insert("2".into()), "1!");
+            assert_eq!(e.get(), "2");
+            assert_eq!(e.remove(), "2");
+        }
+        HstoreEntry::Vacant(_) => panic!("a is present"),
+    }
+    assert!(m.is_empty());
+
+    m.entry_str("b").or_insert_with(|| "3".into());
+    assert_eq!(m["b"], "3");
+}
+
+#[test]
+fn insert_str() {
+    let mut m = Hstore::new();
+
+    assert_eq!(m.insert_str("a", "1"), None);
+    assert_eq!(m.insert_str("a", "2"), Some("1".to_string()));
+    assert_eq!(m.len(), 1);
+    assert_eq!(m["a"], "2");
+}
+
+#[test]
+fn insert_any() {
+    let mut m = Hstore::new();
+
+    assert_eq!(m.insert_any("a", String::from("1")), None);
+    assert_eq!(m.insert_any(String::from("a"), "2"), Some("1".to_string()));
+    assert_eq!(m.len(), 1);
+    assert_eq!(m["a"], "2");
+}
+
+fn kv(key: &str, value: &str) -> KeyValue {
+    KeyValue { key: key.into(), value: value.into() }
+}
+
+#[test]
+fn kv_list_round_trip() {
+    let mut m = Hstore::new();
+    m.insert("b".into(), "2".into());
+    m.insert("a".into(), "1".into());
+    m.insert("c".into(), "".into());
+
+    let list = m.to_kv_list();
+
+    assert_eq!(list, vec![kv("a", "1"), kv("b", "2"), kv("c", "")]);
+    assert_eq!(Hstore::from_kv_list(&list), m);
+    assert!(Hstore::new().to_kv_list().is_empty());
+}
+
+#[test]
+fn kv_list_duplicate_keys() {
+    let m = Hstore::from_kv_list(&[kv("a", "1"), kv("b", "2"), kv("a", "3")]);
+
+    assert_eq!(m.len(), 2);
+    assert_eq!(m["a"], "3");
+    assert_eq!(m["b"], "2");
+}
+
+#[test]
+fn is_superset() {
+    let m = Hstore::from_array([("a", "1"), ("b", "2")]);
+
+    assert!(m.is_superset(&Hstore::from_array([("a", "1")])));
+    assert!(m.is_superset(&Hstore::new()));
+    assert!(m.is_superset(&m));
+    assert!(!m.is_superset(&Hstore::from_array([("a", "2")])));
+    assert!(!m.is_superset(&Hstore::from_array([("c", "1")])));
+}
+
+#[test]
+fn contains_ignore_case() {
+    let m = Hstore::from_array([("theme", "Dark"), ("lang", "ÉN")]);
+    let wanted = Hstore::from_array([("theme", "dark"), ("lang", "én")]);
+
+    assert!(!m.is_superset(&wanted));
+    assert!(m.contains_ignore_case(&wanted));
+    assert!(!m.contains_ignore_case(&Hstore::from_array([("THEME", "dark")])));
+    assert!(!m.contains_ignore_case(&Hstore::from_array([("theme", "light")])));
+}
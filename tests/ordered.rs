@@ -0,0 +1,103 @@
+#![cfg(feature = "indexmap")]
+
+#[macro_use]
+extern crate diesel;
+extern crate diesel_pg_hstore;
+extern crate dotenv;
+
+use std::env;
+
+use diesel::prelude::*;
+use diesel::Connection;
+use diesel::pg::PgConnection;
+use diesel::connection::SimpleConnection;
+
+use diesel_pg_hstore::{Hstore, HstoreOrdered};
+
+fn connection() -> PgConnection {
+    dotenv::dotenv().ok();
+    let database_url = env::var("DATABASE_URL").expect("DATABASE_URL to be defined (may use .env)");
+    let db = PgConnection::establish(&database_url).unwrap();
+    db.begin_test_transaction().unwrap();
+    db
+}
+
+table! {
+    use diesel::types::*;
+    use diesel_pg_hstore::HstoreOrdered;
+
+    ordered_table {
+        id -> Integer,
+        store -> HstoreOrdered,
+    }
+}
+
+#[derive(Insertable, Queryable, Debug, PartialEq)]
+#[table_name = "ordered_table"]
+struct HasOrdered {
+    id: i32,
+    store: HstoreOrdered,
+}
+
+fn ordered(keys: &[&str]) -> HstoreOrdered {
+    keys.iter().map(|k| (k.to_string(), k.to_uppercase())).collect()
+}
+
+#[test]
+fn iteration_follows_insertion_order() {
+    let mut m = HstoreOrdered::new();
+    for k in &["d", "b", "a", "c"] {
+        m.insert(k.to_string(), k.to_uppercase());
+    }
+
+    let keys: Vec<&str> = m.iter().map(|(k, _)| k.as_str()).collect();
+    assert_eq!(keys, vec!["d", "b", "a", "c"]);
+
+    let keys: Vec<String> = m.clone().into_iter().map(|(k, _)| k).collect();
+    assert_eq!(keys, vec!["d", "b", "a", "c"]);
+
+    assert_eq!(m.to_string(), "d=>D, b=>B, a=>A, c=>C");
+}
+
+#[test]
+fn insertion_order_pairs() {
+    let mut m = ordered(&["d", "b", "a", "c"]);
+    m.insert("b".into(), "again".into());
+    m.swap_remove("d");
+    m.insert("d".into(), "last".into());
+
+    let pairs: Vec<(&str, &str)> = m.insertion_order_pairs()
+        .into_iter()
+        .map(|(k, v)| (k.as_str(), v.as_str()))
+        .collect();
+    assert_eq!(pairs, vec![("c", "C"), ("b", "again"), ("a", "A"), ("d", "last")]);
+}
+
+#[test]
+fn converts_from_hstore() {
+    let m = ordered(&["b", "a"]);
+    let hstore = Hstore::from(m.clone());
+    assert_eq!(hstore["a"], "A");
+    assert_eq!(HstoreOrdered::from(hstore), m);
+}
+
+#[test]
+fn round_trip() {
+    let db = connection();
+    db.batch_execute(r#"
+        CREATE EXTENSION IF NOT EXISTS hstore;
+        CREATE TEMPORARY TABLE ordered_table (
+            id SERIAL PRIMARY KEY,
+            store hstore NOT NULL
+        );
+    "#).unwrap();
+
+    let row = HasOrdered { id: 1, store: ordered(&["ccc", "a", "bb", "b"]) };
+    diesel::insert_into(ordered_table::table).values(&row).execute(&db).unwrap();
+
+    // Postgres keeps the entries sorted by key length, then by key
+    let stored: HstoreOrdered = ordered_table::table.select(ordered_table::store).first(&db).unwrap();
+    let keys: Vec<&str> = stored.keys().map(String::as_str).collect();
+    assert_eq!(keys, vec!["a", "b", "bb", "ccc"]);
+    assert_eq!(stored, row.store);
+}
@@ -0,0 +1,28 @@
+extern crate diesel_pg_hstore;
+
+use diesel_pg_hstore::{Hstore, QueryStringError};
+
+#[test]
+fn decoding() {
+    let m = Hstore::from_query_string("a=1&b=hello+world&c=%2B%26%3D&d&&e=").unwrap();
+
+    assert_eq!(m.len(), 5);
+    assert_eq!(m["a"], "1");
+    assert_eq!(m["b"], "hello world");
+    assert_eq!(m["c"], "+&=");
+    assert_eq!(m["d"], "");
+    assert_eq!(m["e"], "");
+}
+
+#[test]
+fn repeated_key() {
+    let m = Hstore::from_query_string("a=1&a=2").unwrap();
+    assert_eq!(m["a"], "2");
+}
+
+#[test]
+fn malformed() {
+    assert_eq!(Hstore::from_query_string("a=1&b=%2"), Err(QueryStringError::InvalidEscape(6)));
+    assert_eq!(Hstore::from_query_string("a=%+1"), Err(QueryStringError::InvalidEscape(2)));
+    assert_eq!(Hstore::from_query_string("a=%FF"), Err(QueryStringError::InvalidUtf8));
+}
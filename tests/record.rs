@@ -0,0 +1,46 @@
+extern crate diesel_pg_hstore;
+
+use diesel_pg_hstore::{FromHstore, Hstore, HstoreFields, HstoreRecordError};
+
+#[derive(Debug, PartialEq)]
+struct Limits {
+    retries: i32,
+    enabled: bool,
+}
+
+impl FromHstore for Limits {
+    fn from_hstore(fields: &mut HstoreFields) -> Result<Self, HstoreRecordError> {
+        Ok(Limits {
+            retries: fields.parse("retries")?,
+            enabled: fields.parse("enabled")?,
+        })
+    }
+}
+
+#[test]
+fn into_struct() {
+    let m = Hstore::from_array([("retries", "5"), ("enabled", "false")]);
+    assert_eq!(m.into_struct::<Limits>(), Ok(Limits { retries: 5, enabled: false }));
+}
+
+#[test]
+fn into_struct_missing_key() {
+    let m = Hstore::from_array([("retries", "5")]);
+    assert_eq!(m.into_struct::<Limits>(), Err(HstoreRecordError::MissingKey("enabled".into())));
+}
+
+#[test]
+fn into_struct_invalid_value() {
+    let m = Hstore::from_array([("retries", "five"), ("enabled", "true")]);
+    assert_eq!(m.into_struct::<Limits>(), Err(HstoreRecordError::InvalidValue {
+        key: "retries".into(),
+        value: "five".into(),
+    }));
+}
+
+#[test]
+fn into_struct_extra_keys() {
+    let m = Hstore::from_array([("retries", "5"), ("enabled", "true"), ("b", ""), ("a", "")]);
+    assert_eq!(m.into_struct::<Limits>(),
+               Err(HstoreRecordError::ExtraKeys(vec!["a".into(), "b".into()])));
+}
@@ -0,0 +1,125 @@
+extern crate diesel_pg_hstore;
+#[cfg(feature = "regex")]
+extern crate regex;
+
+use diesel_pg_hstore::{Hstore, HstoreFlagError, HstoreShape, HstoreShapeError};
+use diesel_pg_hstore::shape::{ValueKind, ValueType};
+
+fn invalid(key: &str, expected: &str) -> HstoreShapeError {
+    HstoreShapeError::InvalidValue { key: key.into(), expected: expected.into() }
+}
+
+#[test]
+fn valid() {
+    let shape = HstoreShape::new()
+        .required("port")
+        .value_type("port", ValueType::Int)
+        .value_type("debug", ValueType::Bool)
+        .value_type("optional", ValueType::NonEmptyString);
+
+    let mut m = Hstore::new();
+    m.insert("port".into(), "-5432".into());
+    m.insert("debug".into(), "false".into());
+    m.insert("extra".into(), "".into());
+
+    assert_eq!(m.validate_shape(&shape), Ok(()));
+    assert_eq!(Hstore::new().validate_shape(&HstoreShape::new()), Ok(()));
+}
+
+#[test]
+fn every_violation_is_reported() {
+    let shape = HstoreShape::new()
+        .required("host")
+        .required("port")
+        .value_type("port", ValueType::Int)
+        .value_type("debug", ValueType::Bool)
+        .value_type("name", ValueType::NonEmptyString)
+        .required("user");
+
+    let mut m = Hstore::new();
+    m.insert("port".into(), "80.5".into());
+    m.insert("debug".into(), "maybe".into());
+    m.insert("name".into(), "".into());
+
+    assert_eq!(m.validate_shape(&shape), Err(vec![
+        HstoreShapeError::MissingKey("host".into()),
+        invalid("port", "an integer"),
+        invalid("debug", "a boolean"),
+        invalid("name", "a non-empty string"),
+        HstoreShapeError::MissingKey("user".into()),
+    ]));
+}
+
+#[test]
+fn bool_value_type_matches_flags() {
+    let shape = HstoreShape::new().value_type("debug", ValueType::Bool);
+
+    for value in &["t", "TRUE", "yes", "on", "1", " f ", "no", "off", "0", "maybe", ""] {
+        let mut m = Hstore::new();
+        m.insert("debug".into(), value.to_string());
+        assert_eq!(m.validate_shape(&shape).is_ok(), m.as_flags().is_ok(), "{:?}", value);
+    }
+}
+
+#[cfg(feature = "regex")]
+#[test]
+fn regex_value_type() {
+    let shape = HstoreShape::new()
+        .value_type("color", ValueType::Regex(regex::Regex::new("^#[0-9a-f]{6}$").unwrap()));
+
+    let mut m = Hstore::new();
+    m.insert("color".into(), "#00ff00".into());
+    assert_eq!(m.validate_shape(&shape), Ok(()));
+
+    m.insert("color".into(), "green".into());
+    assert_eq!(m.validate_shape(&shape), Err(vec![invalid("color", "a match for \"^#[0-9a-f]{6}$\"")]));
+}
+
+#[test]
+fn type_drift() {
+    let mut before = Hstore::new();
+    before.insert("retries".into(), "5".into());
+    before.insert("debug".into(), "true".into());
+    before.insert("name".into(), "app".into());
+    before.insert("removed".into(), "1".into());
+    let mut after = Hstore::new();
+    after.insert("retries".into(), "five".into());
+    after.insert("debug".into(), "false".into());
+    after.insert("name".into(), "web".into());
+    after.insert("added".into(), "true".into());
+
+    assert_eq!(before.type_drift(&after, ValueKind::of), vec!["retries".to_string()]);
+    assert_eq!(before.type_drift(&before, ValueKind::of), Vec::<String>::new());
+}
+
+#[test]
+fn as_flags() {
+    let mut m = Hstore::new();
+    m.insert("a".into(), "t".into());
+    m.insert("b".into(), "f".into());
+    m.insert("c".into(), "TRUE".into());
+    m.insert("d".into(), " off ".into());
+    m.insert("e".into(), "1".into());
+
+    let flags = m.as_flags().unwrap();
+
+    assert_eq!(flags.len(), 5);
+    assert_eq!(flags["a"], true);
+    assert_eq!(flags["b"], false);
+    assert_eq!(flags["c"], true);
+    assert_eq!(flags["d"], false);
+    assert_eq!(flags["e"], true);
+}
+
+#[test]
+fn as_flags_invalid_value() {
+    let mut m = Hstore::new();
+    m.insert("beta".into(), "t".into());
+    m.insert("retries".into(), "5".into());
+    m.insert("mode".into(), "maybe".into());
+
+    let error = m.as_flags().unwrap_err();
+
+    assert_eq!(error, HstoreFlagError { key: "mode".into(), value: "maybe".into() });
+    assert_eq!(error.to_string(), "expected a boolean for key \"mode\", found \"maybe\"");
+}
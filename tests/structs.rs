@@ -0,0 +1,186 @@
+#![cfg(feature = "serde")]
+
+extern crate diesel_pg_hstore;
+#[macro_use]
+extern crate serde_derive;
+#[cfg(feature = "json")]
+#[macro_use]
+extern crate serde_json;
+
+use diesel_pg_hstore::{Hstore, HstoreNullable, HstoreSerdeError, KeyValue};
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+enum Theme {
+    Dark,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct Settings {
+    name: String,
+    port: u16,
+    ratio: f64,
+    debug: bool,
+    theme: Theme,
+    proxy: Option<String>,
+}
+
+#[derive(Serialize)]
+struct Nested {
+    name: String,
+    inner: Inner,
+}
+
+#[derive(Serialize)]
+struct Inner {
+    depth: u8,
+}
+
+#[test]
+fn from_serialize_flat_struct() {
+    let settings = Settings {
+        name: "app".into(),
+        port: 8080,
+        ratio: 0.5,
+        debug: false,
+        theme: Theme::Dark,
+        proxy: None,
+    };
+
+    let m = Hstore::from_serialize(&settings).unwrap();
+
+    assert_eq!(m.len(), 5);
+    assert_eq!(m["name"], "app");
+    assert_eq!(m["port"], "8080");
+    assert_eq!(m["ratio"], "0.5");
+    assert_eq!(m["debug"], "false");
+    assert_eq!(m["theme"], "Dark");
+    assert!(!m.contains_key("proxy"));
+}
+
+#[test]
+fn from_serialize_nested_field() {
+    let nested = Nested { name: "app".into(), inner: Inner { depth: 1 } };
+
+    assert_eq!(Hstore::from_serialize(&nested), Err(HstoreSerdeError::NestedValue("inner".into())));
+}
+
+#[test]
+fn from_serialize_sequence_field() {
+    let mut m = ::std::collections::BTreeMap::new();
+    m.insert("tags", vec!["a", "b"]);
+
+    assert_eq!(Hstore::from_serialize(&m), Err(HstoreSerdeError::NestedValue("tags".into())));
+}
+
+#[test]
+fn from_serialize_not_a_map() {
+    assert_eq!(Hstore::from_serialize(&42), Err(HstoreSerdeError::NotAMap));
+}
+
+#[test]
+fn to_deserialize_typed_fields() {
+    let mut m = Hstore::new();
+    m.insert("name".into(), "app".into());
+    m.insert("port".into(), "5".into());
+    m.insert("ratio".into(), "0.5".into());
+    m.insert("debug".into(), "true".into());
+    m.insert("theme".into(), "Dark".into());
+
+    let settings: Settings = m.to_deserialize().unwrap();
+
+    assert_eq!(settings, Settings {
+        name: "app".into(),
+        port: 5,
+        ratio: 0.5,
+        debug: true,
+        theme: Theme::Dark,
+        proxy: None,
+    });
+}
+
+#[test]
+fn to_deserialize_round_trip() {
+    let settings = Settings {
+        name: "app".into(),
+        port: 8080,
+        ratio: 1.25,
+        debug: false,
+        theme: Theme::Dark,
+        proxy: Some("socks5://localhost".into()),
+    };
+
+    let m = Hstore::from_serialize(&settings).unwrap();
+
+    assert_eq!(m.to_deserialize::<Settings>().unwrap(), settings);
+}
+
+#[test]
+fn to_deserialize_invalid_value() {
+    let mut m = Hstore::new();
+    m.insert("name".into(), "app".into());
+    m.insert("port".into(), "eighty".into());
+    m.insert("ratio".into(), "0.5".into());
+    m.insert("debug".into(), "true".into());
+    m.insert("theme".into(), "Dark".into());
+
+    match m.to_deserialize::<Settings>() {
+        Err(HstoreSerdeError::Custom(msg)) => assert!(msg.contains("eighty"), msg),
+        other => panic!("unexpected result {:?}", other),
+    }
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn json_shape() {
+    let mut m = Hstore::new();
+    m.insert("a".into(), "1".into());
+    m.insert("b".into(), "".into());
+
+    assert_eq!(serde_json::to_value(&m).unwrap(), json!({"a": "1", "b": ""}));
+    assert_eq!(serde_json::to_string(&Hstore::new()).unwrap(), "{}");
+
+    let mut single = Hstore::new();
+    single.insert("k".into(), "v".into());
+    assert_eq!(serde_json::to_string(&single).unwrap(), r#"{"k":"v"}"#);
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn json_round_trip() {
+    let mut m = Hstore::new();
+    m.insert("a".into(), "1".into());
+    m.insert("quote\"d".into(), "line\nbreak".into());
+
+    let json = serde_json::to_string(&m).unwrap();
+    assert_eq!(serde_json::from_str::<Hstore>(&json).unwrap(), m);
+
+    assert!(serde_json::from_str::<Hstore>(r#"{"a": 1}"#).is_err());
+    assert!(serde_json::from_str::<Hstore>(r#"["a", "1"]"#).is_err());
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn json_nullable_round_trip() {
+    let mut m = HstoreNullable::new();
+    m.insert("a".into(), Some("1".into()));
+    m.insert("b".into(), None);
+
+    assert_eq!(serde_json::to_value(&m).unwrap(), json!({"a": "1", "b": null}));
+    assert_eq!(serde_json::from_value::<HstoreNullable>(json!({"a": "1", "b": null})).unwrap(), m);
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn json_kv_list() {
+    let mut m = Hstore::new();
+    m.insert("b".into(), "2".into());
+    m.insert("a".into(), "1".into());
+
+    let json = serde_json::to_value(&m.to_kv_list()).unwrap();
+    assert_eq!(json, json!([{"key": "a", "value": "1"}, {"key": "b", "value": "2"}]));
+
+    let list: Vec<KeyValue> = serde_json::from_value(json).unwrap();
+    assert_eq!(Hstore::from_kv_list(&list), m);
+
+    assert!(serde_json::from_value::<KeyValue>(json!({"key": "a"})).is_err());
+}
@@ -0,0 +1,160 @@
+extern crate diesel_pg_hstore;
+
+use diesel_pg_hstore::{Hstore, HstoreNullable, HstoreParseError};
+
+#[test]
+fn empty() {
+    assert!(Hstore::from_text("").unwrap().is_empty());
+    assert!(Hstore::from_text("  ").unwrap().is_empty());
+}
+
+#[test]
+fn unquoted() {
+    let m = Hstore::from_text("a=>1, b => 2,c=>x=y").unwrap();
+
+    assert_eq!(m.len(), 3);
+    assert_eq!(m["a"], "1");
+    assert_eq!(m["b"], "2");
+    assert_eq!(m["c"], "x=y");
+}
+
+#[test]
+fn quoted() {
+    let m = Hstore::from_text(r#""a b"=>"1, 2", "q\"uote"=>"back\\slash", ""=>"""#).unwrap();
+
+    assert_eq!(m.len(), 3);
+    assert_eq!(m["a b"], "1, 2");
+    assert_eq!(m["q\"uote"], "back\\slash");
+    assert_eq!(m[""], "");
+}
+
+#[test]
+fn null_values_are_ignored() {
+    let m = Hstore::from_text(r#"a=>NULL, b=>null, c=>"NULL""#).unwrap();
+
+    assert_eq!(m.len(), 1);
+    assert_eq!(m["c"], "NULL");
+}
+
+#[test]
+fn trailing_comma() {
+    let m = Hstore::from_text("a=>1, ").unwrap();
+
+    assert_eq!(m.len(), 1);
+    assert_eq!(m["a"], "1");
+}
+
+#[test]
+fn malformed() {
+    assert_eq!(Hstore::from_text(r#""a=>1"#), Err(HstoreParseError::UnterminatedQuote(0)));
+    assert_eq!(Hstore::from_text("a=>"), Err(HstoreParseError::UnexpectedEnd(3)));
+    assert_eq!(Hstore::from_text("=>1"), Err(HstoreParseError::MissingToken(0)));
+    assert_eq!(Hstore::from_text("a=1"), Err(HstoreParseError::MissingArrow(1)));
+    assert_eq!(Hstore::from_text("a=>1 b=>2"), Err(HstoreParseError::TrailingGarbage(5)));
+}
+
+#[test]
+fn from_str() {
+    let m: Hstore = r#"a=>1, "b c" => "", "d\"e"=>"f\\g", h=>NULL"#.parse().unwrap();
+
+    assert_eq!(m.len(), 3);
+    assert_eq!(m["a"], "1");
+    assert_eq!(m["b c"], "");
+    assert_eq!(m["d\"e"], "f\\g");
+
+    assert!("".parse::<Hstore>().unwrap().is_empty());
+    assert_eq!("a=>1 b".parse::<Hstore>(), Err(HstoreParseError::TrailingGarbage(5)));
+}
+
+#[test]
+fn from_str_nullable() {
+    let m: HstoreNullable = "a=>1, b=>NULL, c=>\"NULL\"".parse().unwrap();
+
+    assert_eq!(m.len(), 3);
+    assert_eq!(m["a"], Some("1".into()));
+    assert_eq!(m["b"], None);
+    assert_eq!(m["c"], Some("NULL".into()));
+}
+
+#[test]
+fn display() {
+    let mut m = Hstore::new();
+    m.insert("b".into(), "2".into());
+    m.insert("a".into(), "1".into());
+    m.insert("q\"uote".into(), "back\\slash".into());
+    m.insert("".into(), "NULL".into());
+    m.insert("x=>y".into(), "1, 2".into());
+
+    assert_eq!(m.to_string(),
+               r#"""=>"NULL", a=>1, b=>2, "q\"uote"=>"back\\slash", "x=>y"=>"1, 2""#);
+    assert_eq!(Hstore::new().to_string(), "");
+}
+
+#[test]
+fn display_round_trips() {
+    let mut m = Hstore::new();
+    m.insert(" spaced ".into(), "\"".into());
+    m.insert("null".into(), "".into());
+    m.insert("tab\t".into(), "\\\\".into());
+
+    assert_eq!(m.to_string().parse::<Hstore>().unwrap(), m);
+    assert!(Hstore::new().to_string().parse::<Hstore>().unwrap().is_empty());
+}
+
+#[test]
+fn diff_report() {
+    let mut old = Hstore::new();
+    old.insert("same".into(), "1".into());
+    old.insert("changed".into(), "before".into());
+    old.insert("removed".into(), "gone".into());
+    let mut new = Hstore::new();
+    new.insert("same".into(), "1".into());
+    new.insert("changed".into(), "after".into());
+    new.insert("added".into(), "say \"hi\"".into());
+
+    assert_eq!(old.diff_report(&new), concat!(
+        "+ \"added\" = \"say \\\"hi\\\"\"\n",
+        "~ \"changed\" = \"before\" → \"after\"\n",
+        "- \"removed\" = \"gone\"\n",
+    ));
+    assert_eq!(old.diff_report(&old), "");
+}
+
+#[test]
+fn summary() {
+    let mut m = Hstore::new();
+    m.insert("b".into(), "2".into());
+    m.insert("a".into(), "1".into());
+    m.insert("c".into(), r#"back\slash"#.into());
+
+    assert_eq!(m.summary(3), r#"a=>1, b=>2, c=>"back\\slash""#);
+    assert_eq!(m.summary(10), m.summary(3));
+    assert_eq!(m.summary(1), "a=>1, … (2 more)");
+    assert_eq!(m.summary(0), "… (3 more)");
+    assert_eq!(Hstore::new().summary(0), "");
+}
+
+#[test]
+fn summary_round_trips() {
+    let mut m = Hstore::new();
+    m.insert("quote\"".into(), "a, b=>c".into());
+    m.insert("".into(), "NULL".into());
+
+    assert_eq!(Hstore::from_text(&m.summary(2)).unwrap(), m);
+}
+
+#[test]
+fn search_text() {
+    let mut m = Hstore::new();
+    m.insert("title".into(), "Rust".into());
+    m.insert("tags".into(), "db, sql".into());
+    m.insert("empty".into(), "".into());
+
+    let text = m.to_search_text('|');
+    for (k, v) in &m {
+        assert!(text.contains(k.as_str()));
+        assert!(text.contains(v.as_str()));
+    }
+    assert_eq!(text, "empty||tags|db, sql|title|Rust");
+    assert_eq!(Hstore::new().to_search_text(' '), "");
+}
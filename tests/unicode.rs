@@ -0,0 +1,28 @@
+#![cfg(feature = "unicode")]
+
+extern crate diesel_pg_hstore;
+
+use diesel_pg_hstore::Hstore;
+
+#[test]
+fn normalize_values() {
+    let mut m = Hstore::new();
+    m.insert("name".into(), "Jose\u{301}".into());
+
+    m.normalize_unicode();
+
+    assert_eq!(m["name"], "Jos\u{e9}");
+}
+
+#[test]
+fn equivalent_keys_are_merged() {
+    let mut m = Hstore::new();
+    m.insert("caf\u{e9}".into(), "composed".into());
+    m.insert("cafe\u{301}".into(), "decomposed".into());
+    assert_eq!(m.len(), 2);
+
+    m.normalize_unicode();
+
+    assert_eq!(m.len(), 1);
+    assert_eq!(m["caf\u{e9}"], "composed");
+}
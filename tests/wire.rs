@@ -0,0 +1,290 @@
+extern crate diesel_pg_hstore;
+
+use std::collections::HashMap;
+use std::io;
+
+use diesel_pg_hstore::{Hstore, HstoreDecodeStats, HstoreLengthOverflow, HstoreWireError};
+
+/// Encode entries by hand, so that null values can be included
+fn wire(entries: &[(&str, Option<&str>)]) -> Vec<u8> {
+    fn length(buf: &mut Vec<u8>, len: i32) {
+        buf.extend_from_slice(&[(len >> 24) as u8, (len >> 16) as u8, (len >> 8) as u8, len as u8]);
+    }
+
+    let mut buf = Vec::new();
+    length(&mut buf, entries.len() as i32);
+    for &(k, v) in entries {
+        length(&mut buf, k.len() as i32);
+        buf.extend_from_slice(k.as_bytes());
+        match v {
+            Some(v) => {
+                length(&mut buf, v.len() as i32);
+                buf.extend_from_slice(v.as_bytes());
+            }
+            None => length(&mut buf, -1),
+        }
+    }
+    buf
+}
+
+#[test]
+fn round_trip() {
+    let mut m = Hstore::new();
+    m.insert("a".into(), "1".into());
+    m.insert("".into(), "".into());
+    m.insert("ключ".into(), "値".into());
+
+    let bytes = m.to_wire_bytes().unwrap();
+    assert_eq!(Hstore::from_wire_bytes(&bytes).unwrap(), m);
+}
+
+#[test]
+fn large_value_round_trip() {
+    let value: String = "abcdefghijklmnopqrstuvwxyz0123456789+/".chars()
+        .cycle()
+        .take(2 * 1024 * 1024)
+        .collect();
+    let mut m = Hstore::new();
+    m.insert("blob".into(), value.clone());
+    m.insert("small".into(), "1".into());
+
+    let bytes = m.to_wire_bytes().unwrap();
+    assert_eq!(bytes.len(), 4 + (4 + 4 + 4 + value.len()) + (4 + 5 + 4 + 1));
+
+    let decoded = Hstore::from_wire_bytes(&bytes).unwrap();
+    assert_eq!(decoded["blob"].len(), 2 * 1024 * 1024);
+    assert_eq!(decoded, m);
+}
+
+#[test]
+fn many_entries_round_trip() {
+    let m: Hstore = (0..10000).map(|i| (format!("key{}", i), i.to_string())).collect();
+
+    let bytes = m.to_wire_bytes().unwrap();
+    assert_eq!(bytes.len(), m.wire_size());
+    assert_eq!(bytes.capacity(), m.wire_size());
+    assert_eq!(Hstore::from_wire_bytes(&bytes).unwrap(), m);
+}
+
+#[test]
+fn try_from_hashmap() {
+    let mut hm = HashMap::new();
+    hm.insert("small".to_string(), "1".to_string());
+    assert_eq!(Hstore::try_from_hashmap(hm.clone()), Ok(Hstore::from_hashmap(hm)));
+}
+
+#[test]
+#[ignore]
+fn oversized_value() {
+    // Allocates and scans over 2GB, so this needs a 64 bit target and enough memory
+    let len = i32::max_value() as usize + 1;
+    let mut hm = HashMap::new();
+    hm.insert("big".to_string(), String::from_utf8(vec![0; len]).unwrap());
+    let m = Hstore::from_hashmap(hm);
+
+    let e = m.write_wire_bytes(&mut io::sink()).unwrap_err();
+    assert_eq!(e.downcast_ref::<HstoreLengthOverflow>(), Some(&HstoreLengthOverflow { len: len }));
+
+    assert_eq!(Hstore::try_from_hashmap(m.into()), Err(HstoreLengthOverflow { len: len }));
+}
+
+#[test]
+#[ignore]
+fn longest_key() {
+    // Allocates a 2GB key, see the unit tests of wire_length for the cheap check
+    let len = i32::max_value() as usize;
+    let mut hm = HashMap::new();
+    hm.insert(String::from_utf8(vec![0; len]).unwrap(), String::new());
+    let m = Hstore::try_from_hashmap(hm).expect("i32::MAX bytes to fit");
+
+    // Only the length prefix is checked here: the key itself is not written out
+    struct Prefix(Vec<u8>);
+    impl io::Write for Prefix {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            if self.0.len() < 8 {
+                self.0.extend_from_slice(&buf[..buf.len().min(8 - self.0.len())]);
+            }
+            Ok(buf.len())
+        }
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let mut prefix = Prefix(Vec::new());
+    m.write_wire_bytes(&mut prefix).unwrap();
+    assert_eq!(prefix.0, vec![0, 0, 0, 1, 0x7f, 0xff, 0xff, 0xff]);
+}
+
+#[test]
+fn truncated_buffer_is_an_error() {
+    let mut m = Hstore::new();
+    m.insert("key".into(), "value".into());
+
+    let bytes = m.to_wire_bytes().unwrap();
+    for len in 0..bytes.len() {
+        assert!(Hstore::from_wire_bytes(&bytes[..len]).is_err());
+    }
+}
+
+#[test]
+fn malformed_buffer_errors() {
+    fn error(buf: &[u8]) -> HstoreWireError {
+        let e = Hstore::from_wire_bytes(buf).unwrap_err();
+        *e.downcast_ref::<HstoreWireError>().expect("an HstoreWireError")
+    }
+
+    assert_eq!(error(&[0, 0]), HstoreWireError::Truncated);
+    assert_eq!(error(&[255, 255, 255, 255]), HstoreWireError::NegativeCount(-1));
+
+    let mut negative_key = wire(&[]);
+    negative_key[3] = 1;
+    negative_key.extend_from_slice(&[255, 255, 255, 254]);
+    assert_eq!(error(&negative_key), HstoreWireError::InvalidKeyLength(-2));
+
+    let mut long_key = wire(&[("a", Some("1"))]);
+    long_key[7] = 10;
+    assert_eq!(error(&long_key), HstoreWireError::InvalidKeyLength(10));
+
+    let mut long_value = wire(&[("a", Some("1"))]);
+    long_value[12] = 5;
+    assert_eq!(error(&long_value), HstoreWireError::InvalidValueLength(5));
+
+    let mut trailing = wire(&[("a", Some("1"))]);
+    trailing.extend_from_slice(b"xy");
+    assert_eq!(error(&trailing), HstoreWireError::TrailingBytes(2));
+
+    let mut invalid_utf8 = wire(&[("a", Some("1"))]);
+    invalid_utf8[8] = 0xff;
+    match error(&invalid_utf8) {
+        HstoreWireError::Utf8 { entry: 0, offset: 8, .. } => {}
+        e => panic!("expected a UTF-8 error, got {:?}", e),
+    }
+}
+
+#[test]
+fn invalid_utf8_offset() {
+    let mut bytes = wire(&[("a", Some("1")), ("b", Some("xyz"))]);
+    // count, then "a" => "1" in 4 + 1 + 4 + 1 bytes, then the length of "b" and "b" itself, then
+    // the length of "xyz"
+    let value = 4 + 10 + 5 + 4;
+    assert_eq!(&bytes[value..], b"xyz");
+    bytes[value + 1] = 0xc0;
+
+    match Hstore::extract_key_from_wire(&bytes, "c") {
+        Err(HstoreWireError::Utf8 { entry, offset, error }) => {
+            assert_eq!(entry, 1);
+            assert_eq!(offset, value + 1);
+            assert_eq!(error.valid_up_to(), 1);
+        }
+        r => panic!("expected a UTF-8 error, got {:?}", r),
+    }
+
+    let e = Hstore::from_wire_bytes(&bytes).unwrap_err();
+    assert!(e.to_string().starts_with("invalid UTF-8 in hstore at byte 24 in entry 1: "));
+}
+
+#[test]
+fn encode_subset() {
+    let mut m = Hstore::new();
+    m.insert("a".into(), "1".into());
+    m.insert("b".into(), "2".into());
+    m.insert("c".into(), "3".into());
+
+    let bytes = m.encode_subset(&["a", "c", "missing", "a"]).unwrap();
+    let subset = Hstore::from_wire_bytes(&bytes).unwrap();
+
+    assert_eq!(subset.len(), 2);
+    assert_eq!(subset["a"], "1");
+    assert_eq!(subset["c"], "3");
+}
+
+#[test]
+fn extract_key_from_wire() {
+    let bytes = wire(&[("a", Some("1")), ("b", None), ("c", Some("3"))]);
+
+    assert_eq!(Hstore::extract_key_from_wire(&bytes, "a"), Ok(Some("1".to_string())));
+    assert_eq!(Hstore::extract_key_from_wire(&bytes, "c"), Ok(Some("3".to_string())));
+    assert_eq!(Hstore::extract_key_from_wire(&bytes, "b"), Ok(None));
+    assert_eq!(Hstore::extract_key_from_wire(&bytes, "missing"), Ok(None));
+
+    assert_eq!(Hstore::extract_key_from_wire(&bytes[..bytes.len() - 1], "missing"),
+               Err(HstoreWireError::InvalidValueLength(1)));
+    assert_eq!(Hstore::extract_key_from_wire(&bytes[..2], "a"), Err(HstoreWireError::Truncated));
+}
+
+#[test]
+fn extract_key_from_wire_repeated_key() {
+    let bytes = wire(&[("a", Some("1")), ("b", Some("1")), ("a", Some("2")), ("b", None)]);
+    let decoded = Hstore::from_wire_bytes(&bytes).unwrap();
+
+    assert_eq!(Hstore::extract_key_from_wire(&bytes, "a"), Ok(Some("2".to_string())));
+    assert_eq!(Hstore::extract_key_from_wire(&bytes, "b"), Ok(Some("1".to_string())));
+    assert_eq!(decoded["a"], "2");
+    assert_eq!(decoded["b"], "1");
+}
+
+#[test]
+fn decode_stats() {
+    let bytes = wire(&[("a", Some("1")), ("bb", None), ("ccc", Some("333")), ("d", None)]);
+
+    assert_eq!(Hstore::decode_stats(&bytes), Ok(HstoreDecodeStats {
+        entries: 4,
+        null_values: 2,
+        key_bytes: 7,
+        value_bytes: 4,
+    }));
+    assert_eq!(Hstore::decode_stats(&wire(&[])), Ok(HstoreDecodeStats::default()));
+
+    let mut trailing = bytes.clone();
+    trailing.push(0);
+    assert_eq!(Hstore::decode_stats(&trailing), Err(HstoreWireError::TrailingBytes(1)));
+}
+
+#[test]
+fn copy_binary_field() {
+    let mut m = Hstore::new();
+    m.insert("a".into(), "1".into());
+    m.insert("key".into(), "value".into());
+
+    let mut buf = vec![0xff];
+    m.to_copy_binary_field(&mut buf).unwrap();
+    assert_eq!(buf.len(), 1 + 4 + m.wire_size());
+    assert_eq!(Hstore::from_copy_binary_field(&buf[1..]), Ok(Some(m.clone())));
+
+    let len = buf.len();
+    assert_eq!(Hstore::from_copy_binary_field(&buf[1..len - 1]), Err(HstoreWireError::Truncated));
+    buf.push(0);
+    assert_eq!(Hstore::from_copy_binary_field(&buf[1..]), Err(HstoreWireError::TrailingBytes(1)));
+
+    assert_eq!(Hstore::from_copy_binary_field(&[0xff, 0xff, 0xff, 0xff]), Ok(None));
+}
+
+#[test]
+#[ignore]
+fn copy_binary_field_error_leaves_buffer() {
+    // Allocates and scans over 2GB
+    let mut m = Hstore::new();
+    m.insert("big".into(), String::from_utf8(vec![0; i32::max_value() as usize]).unwrap());
+
+    let mut buf = vec![0xff];
+    assert!(m.to_copy_binary_field(&mut buf).is_err());
+    assert_eq!(buf, vec![0xff]);
+}
+
+#[test]
+fn canonical_bytes() {
+    let mut a = Hstore::with_capacity(64);
+    for k in &["d", "a", "c", "b"] {
+        a.insert(k.to_string(), k.to_uppercase());
+    }
+    let mut b = Hstore::new();
+    for k in &["b", "c", "a", "d"] {
+        b.insert(k.to_string(), k.to_uppercase());
+    }
+
+    assert_eq!(a.canonical_bytes(), b.canonical_bytes());
+    assert_eq!(a.canonical_bytes(), wire(&[("a", Some("A")), ("b", Some("B")), ("c", Some("C")),
+                                           ("d", Some("D"))]));
+    assert_eq!(Hstore::from_wire_bytes(&a.canonical_bytes()).unwrap(), b);
+}